@@ -68,29 +68,36 @@ pub async fn get_connection_to_modify<'a>(
 ) -> anyhow::Result<BranchConnection<'a>> {
     match get_connection_that_is_not(branch, options, connection).await {
         Ok(connection) => Ok(connection),
-        Err(_) => {
-            let temp_name = Uuid::new_v4().to_string();
-            connection
-                .execute(
-                    &format!(
-                        "create empty branch {}",
-                        edgeql_parser::helpers::quote_name(&temp_name)
-                    ),
-                    &(),
-                )
-                .await?;
-
-            let mut conn_params = options.conn_params.clone();
-            Ok(BranchConnection {
-                connection: conn_params.branch(&temp_name)?.connect().await?,
-                options,
-                branch_name: temp_name,
-                is_temp: true,
-            })
-        }
+        Err(_) => create_temp_branch(options, connection).await,
     }
 }
 
+/// Creates an empty branch with a random name, which is dropped by
+/// `BranchConnection::clean`.
+pub async fn create_temp_branch<'a>(
+    options: &'a Options,
+    connection: &mut Connection,
+) -> anyhow::Result<BranchConnection<'a>> {
+    let temp_name = Uuid::new_v4().to_string();
+    connection
+        .execute(
+            &format!(
+                "create empty branch {}",
+                edgeql_parser::helpers::quote_name(&temp_name)
+            ),
+            &(),
+        )
+        .await?;
+
+    let mut conn_params = options.conn_params.clone();
+    Ok(BranchConnection {
+        connection: conn_params.branch(&temp_name)?.connect().await?,
+        options,
+        branch_name: temp_name,
+        is_temp: true,
+    })
+}
+
 pub async fn get_connection_that_is_not<'a>(
     target_branch: &str,
     options: &'a Options,
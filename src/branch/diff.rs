@@ -0,0 +1,302 @@
+use std::collections::BTreeMap;
+
+use colorful::Colorful;
+
+use crate::branch::connections::connect_if_branch_exists;
+use crate::branch::context::Context;
+use crate::branch::option::Diff;
+use crate::commands::Options;
+use crate::connect::Connection;
+use crate::migrations::rebase::split_migrations;
+use crate::print;
+
+/// Schema items as `(kind, name, definition)`. Items without a name of
+/// their own (indexes, constraints) are named after their subject.
+///
+/// Builtin items, items of extensions and internal items (with names
+/// like `__derived__`) are skipped.
+const SCHEMA_ITEMS: &str = r###"
+    WITH
+        MODULE schema,
+        system := "^(?:std|schema|math|sys|cfg|cal|fts|pg|ext|stdgraphql)::"
+            ++ "|(?:^|::|[.])__",
+        T := (
+            SELECT ObjectType
+            FILTER NOT .is_compound_type AND NOT .is_from_alias
+                AND NOT .builtin AND NOT re_test(system, .name)
+        )
+    SELECT {
+        (
+            FOR t IN T UNION (
+                'type',
+                t.name,
+                (IF t.is_abstract THEN 'abstract ' ELSE '')
+                ++ 'extending '
+                ++ to_str(array_agg((SELECT t.bases ORDER BY .name).name), ', ')
+            )
+        ),
+        (
+            FOR s IN (
+                SELECT ScalarType
+                FILTER NOT .is_from_alias AND NOT .builtin
+                    AND NOT re_test(system, .name)
+            ) UNION (
+                'scalar type',
+                s.name,
+                (IF s.is_abstract THEN 'abstract ' ELSE '')
+                ++ 'extending '
+                ++ to_str(array_agg((SELECT s.bases ORDER BY .name).name), ', ')
+                ++ ((' enum<' ++ to_str(s.enum_values, ', ') ++ '>') ?? '')
+            )
+        ),
+        (
+            FOR t IN T UNION (
+                FOR p IN (
+                    SELECT t.pointers
+                    FILTER @owned AND .name NOT IN {'id', '__type__'}
+                ) UNION (
+                    'property' IF p IS Property ELSE 'link',
+                    t.name ++ '.' ++ p.name,
+                    (IF p.required THEN 'required ' ELSE 'optional ')
+                    ++ (IF <str>p.cardinality = 'Many' THEN 'multi ' ELSE 'single ')
+                    ++ (p.target.name ?? '')
+                    ++ ((' := ' ++ p.expr) ?? '')
+                    ++ ((' default ' ++ p.default) ?? '')
+                )
+            )
+        ),
+        (
+            FOR t IN T UNION (
+                FOR i IN t.indexes UNION (
+                    'index',
+                    t.name ++ ' on ' ++ i.expr,
+                    i.expr
+                )
+            )
+        ),
+        (
+            FOR c IN (
+                SELECT Constraint FILTER NOT .is_abstract AND NOT .builtin
+            ) UNION (
+                WITH subject := (
+                    (c.subject[IS Pointer].<pointers[IS Source].name
+                     ++ '.' ++ c.subject.name)
+                    ?? c.subject.name
+                )
+                SELECT (
+                    'constraint',
+                    subject ++ ' ' ++ c.name,
+                    c.name
+                    ++ '(' ++ to_str(array_agg(c.params@value), ', ') ++ ')'
+                    ++ ((' on ' ++ c.subjectexpr) ?? '')
+                    ++ ((' except ' ++ c.except_expr) ?? '')
+                )
+                FILTER NOT re_test(system, subject)
+            )
+        ),
+        (
+            FOR f IN (
+                SELECT Function FILTER NOT .builtin AND NOT re_test(system, .name)
+            ) UNION (
+                'function',
+                f.name ++ '('
+                ++ to_str(array_agg((SELECT f.params ORDER BY .num).type.name), ', ')
+                ++ ')',
+                '-> ' ++ <str>f.return_typemod ++ ' ' ++ f.return_type.name
+                ++ ((' using ' ++ f.body) ?? '')
+            )
+        ),
+    }
+"###;
+
+#[derive(Debug, PartialEq)]
+enum Change<'a> {
+    Added(&'a str),
+    Removed(&'a str),
+    Altered { old: &'a str, new: &'a str },
+}
+
+/// Item definitions by `(name, kind)`, so that members are listed right
+/// after their type
+type Items = BTreeMap<(String, String), String>;
+
+pub async fn main(
+    options: &Diff,
+    context: &Context,
+    connection: &mut Connection,
+    cli_opts: &Options,
+) -> anyhow::Result<()> {
+    let (mut base, mut other) = match &options.other_branch {
+        Some(other) => {
+            if &options.branch == other {
+                anyhow::bail!("Cannot compare a branch with itself");
+            }
+            (
+                Some(connect(cli_opts, &options.branch).await?),
+                connect(cli_opts, other).await?,
+            )
+        }
+        None => {
+            let current_branch = context.get_current_branch(connection).await?;
+            if options.branch == current_branch {
+                anyhow::bail!("Cannot compare the current branch with itself");
+            }
+            (None, connect(cli_opts, &options.branch).await?)
+        }
+    };
+    let base = base.as_mut().unwrap_or(connection);
+    let base_name = base.database().to_string();
+    let other_name = other.database().to_string();
+
+    let migrations = split_migrations(base, &mut other).await?;
+    let base_items = schema_items(base).await?;
+    let other_items = schema_items(&mut other).await?;
+    let changes = diff(&base_items, &other_items);
+
+    eprintln!(
+        "Comparing '{}' to '{}'. Last common migration is {}.",
+        other_name,
+        base_name,
+        migrations.last_common().unwrap_or("initial"),
+    );
+    for (branch, list) in [
+        (&base_name, migrations.source_only().collect::<Vec<_>>()),
+        (&other_name, migrations.target_only().collect()),
+    ] {
+        if list.is_empty() {
+            continue;
+        }
+        println!("Migrations only in '{}':", branch);
+        for migration in list {
+            match &migration.message {
+                Some(message) => println!("  {} {}", migration.name, message),
+                None => println!("  {}", migration.name),
+            }
+        }
+    }
+
+    if changes.is_empty() {
+        print::success("No schema changes.");
+        return Ok(());
+    }
+    println!("Schema changes:");
+    for ((name, kind), change) in &changes {
+        match change {
+            Change::Added(def) => {
+                println!("  {} {} {}: {}", marker('+'), kind, name, def);
+            }
+            Change::Removed(def) => {
+                println!("  {} {} {}: {}", marker('-'), kind, name, def);
+            }
+            Change::Altered { old, new } => {
+                println!("  {} {} {}", marker('~'), kind, name);
+                println!("      was: {}", old);
+                println!("      now: {}", new);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn connect(options: &Options, branch: &str) -> anyhow::Result<Connection> {
+    let mut connector = options.conn_params.clone();
+    match connect_if_branch_exists(connector.branch(branch)?).await? {
+        Some(connection) => Ok(connection),
+        None => anyhow::bail!("The branch '{}' doesn't exist", branch),
+    }
+}
+
+async fn schema_items(connection: &mut Connection) -> anyhow::Result<Items> {
+    let rows: Vec<(String, String, String)> = connection.query(SCHEMA_ITEMS, &()).await?;
+    let mut items = Items::new();
+    for (kind, name, definition) in rows {
+        // Several constraints of the same kind can be on the same subject
+        items
+            .entry((name, kind))
+            .and_modify(|def| {
+                let mut defs: Vec<_> = def.split("; ").collect();
+                defs.push(&definition);
+                defs.sort();
+                *def = defs.join("; ");
+            })
+            .or_insert_with(|| definition.clone());
+    }
+    Ok(items)
+}
+
+fn marker(sign: char) -> String {
+    let text = sign.to_string();
+    if !print::use_color() {
+        return text;
+    }
+    match sign {
+        '+' => text.green().to_string(),
+        '-' => text.red().to_string(),
+        _ => text.yellow().to_string(),
+    }
+}
+
+fn diff<'a>(base: &'a Items, other: &'a Items) -> Vec<(&'a (String, String), Change<'a>)> {
+    let removed = base
+        .iter()
+        .filter(|(key, _)| !other.contains_key(*key))
+        .map(|(key, def)| (key, Change::Removed(def)));
+    let changed = other.iter().filter_map(|(key, new)| match base.get(key) {
+        None => Some((key, Change::Added(new))),
+        Some(old) if old != new => Some((key, Change::Altered { old, new })),
+        Some(_) => None,
+    });
+    let mut changes: Vec<_> = removed.chain(changed).collect();
+    changes.sort_by(|a, b| a.0.cmp(b.0));
+    changes
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff, Change, Items};
+
+    fn items(list: &[(&str, &str, &str)]) -> Items {
+        list.iter()
+            .map(|(name, kind, def)| ((name.to_string(), kind.to_string()), def.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn changes() {
+        let base = items(&[
+            ("default::User", "type", "extending std::Object"),
+            (
+                "default::User.age",
+                "property",
+                "optional single std::int32",
+            ),
+            ("default::User.name", "property", "required single std::str"),
+        ]);
+        let other = items(&[
+            ("default::Post", "type", "extending std::Object"),
+            ("default::User", "type", "extending std::Object"),
+            ("default::User.name", "property", "optional single std::str"),
+        ]);
+        let changes: Vec<_> = diff(&base, &other)
+            .into_iter()
+            .map(|((name, _), change)| (&name[..], change))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("default::Post", Change::Added("extending std::Object")),
+                (
+                    "default::User.age",
+                    Change::Removed("optional single std::int32")
+                ),
+                (
+                    "default::User.name",
+                    Change::Altered {
+                        old: "required single std::str",
+                        new: "optional single std::str",
+                    }
+                ),
+            ]
+        );
+    }
+}
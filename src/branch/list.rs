@@ -1,12 +1,52 @@
+use std::cmp::Reverse;
+
+use futures_util::stream::{self, StreamExt};
+
 use crate::branch::context::Context;
-use crate::branch::option::List;
+use crate::branch::option::{List, ListSort};
+use crate::commands::Options;
 use crate::connect::Connection;
-use crossterm::style::Stylize;
+use crate::migrations::rebase::{read_branch_migrations, split_migration_lists, BranchMigrations};
+use crate::table::{self, Attr, Cell, Row, Table};
+
+/// Relation of the branch to the default branch. Variants are ordered
+/// for `--sort=status`.
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+enum Status {
+    Diverged,
+    Behind,
+    Ahead,
+    UpToDate,
+    Default,
+    Unknown,
+}
+
+#[derive(serde::Serialize, Debug)]
+struct BranchInfo {
+    name: String,
+    current: bool,
+    last_migration: Option<String>,
+    migrations: Option<usize>,
+    /// Number of objects estimated from table statistics, so it's cheap
+    /// to get but may be outdated
+    approx_objects: Option<i64>,
+    status: Status,
+    /// Migrations not in the default branch
+    ahead: usize,
+    /// Migrations of the default branch missing in this one
+    behind: usize,
+}
+
+/// Number of branches queried at the same time, each of them needs its
+/// own connection
+const CONCURRENT_BRANCHES: usize = 4;
 
 pub async fn main(
-    _options: &List,
+    options: &List,
     context: &Context,
     connection: &mut Connection,
+    cli_opts: &Options,
 ) -> anyhow::Result<()> {
     let current_branch = context.get_current_branch(connection).await?;
 
@@ -17,13 +57,263 @@ pub async fn main(
         )
         .await?;
 
-    for branch in branches {
-        if current_branch == branch {
-            println!("{} - Current", branch.green());
-        } else {
-            println!("{}", branch);
+    // Without the default branch statuses are unknown, but the rest of
+    // the info is still there
+    let (default_branch, default_migrations) = match read_default_branch(cli_opts).await {
+        Ok((name, migrations)) => (Some(name), Some(migrations)),
+        Err(e) => {
+            log::warn!("Cannot get info about the default branch: {:#}", e);
+            (None, None)
         }
+    };
+    let default_branch = default_branch.as_deref();
+    let default_migrations = default_migrations.as_ref();
+
+    let mut infos = Vec::with_capacity(branches.len());
+    if branches.contains(&current_branch) {
+        let mut info = BranchInfo::new(current_branch.clone(), true);
+        let result = fill_info(&mut info, default_migrations, connection).await;
+        infos.push(finish_info(info, result, default_branch));
     }
+    let others = stream::iter(branches.into_iter().filter(|b| b != &current_branch))
+        .map(|name| async move {
+            let mut info = BranchInfo::new(name, false);
+            let result = connect_and_fill(&mut info, default_migrations, cli_opts).await;
+            finish_info(info, result, default_branch)
+        })
+        .buffered(CONCURRENT_BRANCHES)
+        .collect::<Vec<_>>()
+        .await;
+    infos.extend(others);
 
+    match options.sort {
+        ListSort::Name => infos.sort_by(|a, b| a.name.cmp(&b.name)),
+        ListSort::Migrations => infos.sort_by_key(|i| Reverse(i.migrations)),
+        ListSort::Objects => infos.sort_by_key(|i| Reverse(i.approx_objects)),
+        ListSort::Status => infos.sort_by_key(|i| i.status),
+    }
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&infos)?);
+    } else {
+        table(&infos).printstd();
+    }
     Ok(())
 }
+
+impl BranchInfo {
+    fn new(name: String, current: bool) -> BranchInfo {
+        BranchInfo {
+            name,
+            current,
+            last_migration: None,
+            migrations: None,
+            approx_objects: None,
+            status: Status::Unknown,
+            ahead: 0,
+            behind: 0,
+        }
+    }
+}
+
+async fn read_default_branch(cli_opts: &Options) -> anyhow::Result<(String, BranchMigrations)> {
+    let mut connector = cli_opts.conn_params.clone();
+    let mut default = connector.branch("__default__")?.connect().await?;
+    let name = default
+        .query_required_single("select sys::get_current_database()", &())
+        .await?;
+    let migrations = read_branch_migrations(&mut default).await?;
+    Ok((name, migrations))
+}
+
+async fn connect_and_fill(
+    info: &mut BranchInfo,
+    default: Option<&BranchMigrations>,
+    cli_opts: &Options,
+) -> anyhow::Result<()> {
+    let mut connector = cli_opts.conn_params.clone();
+    let mut branch = connector.branch(&info.name)?.connect().await?;
+    fill_info(info, default, &mut branch).await
+}
+
+fn finish_info(
+    mut info: BranchInfo,
+    result: anyhow::Result<()>,
+    default_branch: Option<&str>,
+) -> BranchInfo {
+    if let Err(e) = result {
+        log::warn!("Cannot get info about branch {:?}: {:#}", info.name, e);
+    }
+    if Some(&info.name[..]) == default_branch && info.status != Status::Unknown {
+        info.status = Status::Default;
+    }
+    info
+}
+
+async fn fill_info(
+    info: &mut BranchInfo,
+    default: Option<&BranchMigrations>,
+    branch: &mut Connection,
+) -> anyhow::Result<()> {
+    // Counting objects scans every table, while the estimate only reads
+    // the statistics of the tables. There is no cheap way to get the
+    // size of a branch, so the number of objects stands in for it
+    let estimate = branch
+        .query_required_single::<i64, _>(
+            "SELECT sum(sys::approximate_count(
+                (SELECT schema::ObjectType FILTER .name = 'std::Object')
+            ))",
+            &(),
+        )
+        .await;
+    match estimate {
+        // Tables not analyzed yet have a negative estimate
+        Ok(count) => info.approx_objects = Some(count.max(0)),
+        Err(e) => log::warn!("Cannot estimate objects in {:?}: {:#}", info.name, e),
+    }
+    let Some(default) = default else {
+        return Ok(());
+    };
+    let branch_migrations = read_branch_migrations(branch).await?;
+    let migrations = split_migration_lists(default.clone(), branch_migrations)?;
+    info.ahead = migrations.target_only().count();
+    info.behind = migrations.source_only().count();
+    info.last_migration = migrations
+        .target_only()
+        .last()
+        .map(|m| &m.name[..])
+        .or(migrations.last_common())
+        .map(String::from);
+    info.migrations = Some(migrations.base_count() + info.ahead);
+    info.status = match (info.ahead, info.behind) {
+        (0, 0) => Status::UpToDate,
+        (_, 0) => Status::Ahead,
+        (0, _) => Status::Behind,
+        (_, _) => Status::Diverged,
+    };
+    Ok(())
+}
+
+fn table(infos: &[BranchInfo]) -> Table {
+    let mut table = Table::new();
+    table.set_format(*table::FORMAT);
+    table.add_row(Row::new(vec![
+        table::header_cell("Branch"),
+        table::header_cell("Last Migration"),
+        table::header_cell("Migrations"),
+        table::header_cell("Objects (approx.)"),
+        table::header_cell("Status"),
+    ]));
+    for info in infos {
+        let name = if info.current {
+            Cell::new(&format!("{} - Current", info.name)).with_style(Attr::Bold)
+        } else {
+            Cell::new(&info.name)
+        };
+        // Migration names are long hashes, the prefix is enough to
+        // recognize them
+        let last_migration = info
+            .last_migration
+            .as_deref()
+            .map(|m| m.get(..12).unwrap_or(m))
+            .unwrap_or("initial");
+        let status = match info.status {
+            Status::Diverged => format!("diverged (+{} -{})", info.ahead, info.behind),
+            Status::Behind => format!("behind by {}", info.behind),
+            Status::Ahead => format!("ahead by {}", info.ahead),
+            Status::UpToDate => "up to date".into(),
+            Status::Default => "default".into(),
+            Status::Unknown => "?".into(),
+        };
+        table.add_row(Row::new(vec![
+            name,
+            Cell::new(if info.migrations.is_some() {
+                last_migration
+            } else {
+                "?"
+            }),
+            Cell::new(&info.migrations.map_or("?".into(), |n| n.to_string())),
+            Cell::new(&info.approx_objects.map_or("?".into(), |n| n.to_string())),
+            Cell::new(&status),
+        ]));
+    }
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::{table, BranchInfo, Status};
+
+    fn infos() -> Vec<BranchInfo> {
+        vec![
+            BranchInfo {
+                name: "main".into(),
+                current: true,
+                last_migration: Some("m1abcdefghijklmnop".into()),
+                migrations: Some(3),
+                approx_objects: Some(120),
+                status: Status::Default,
+                ahead: 0,
+                behind: 0,
+            },
+            BranchInfo {
+                name: "feature".into(),
+                current: false,
+                last_migration: Some("m1qrstuvwxyzabcdef".into()),
+                migrations: Some(4),
+                approx_objects: Some(0),
+                status: Status::Diverged,
+                ahead: 2,
+                behind: 1,
+            },
+            BranchInfo {
+                name: "broken".into(),
+                current: false,
+                last_migration: None,
+                migrations: None,
+                approx_objects: None,
+                status: Status::Unknown,
+                ahead: 0,
+                behind: 0,
+            },
+        ]
+    }
+
+    #[test]
+    fn render_table() {
+        let text = table(&infos()).to_string();
+        let lines: Vec<_> = text.lines().map(|l| l.trim_end()).collect();
+        assert_eq!(
+            lines,
+            [
+                "┌────────────────┬────────────────┬────────────┬───────────────────┬──────────────────┐",
+                "│ Branch         │ Last Migration │ Migrations │ Objects (approx.) │ Status           │",
+                "│ main - Current │ m1abcdefghij   │ 3          │ 120               │ default          │",
+                "│ feature        │ m1qrstuvwxyz   │ 4          │ 0                 │ diverged (+2 -1) │",
+                "│ broken         │ ?              │ ?          │ ?                 │ ?                │",
+                "└────────────────┴────────────────┴────────────┴───────────────────┴──────────────────┘",
+            ]
+        );
+    }
+
+    #[test]
+    fn json() {
+        let value = serde_json::to_value(&infos()[1]).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "name": "feature",
+                "current": false,
+                "last_migration": "m1qrstuvwxyzabcdef",
+                "migrations": 4,
+                "approx_objects": 0,
+                "status": "diverged",
+                "ahead": 2,
+                "behind": 1,
+            })
+        );
+        let value = serde_json::to_value(&infos()[2]).unwrap();
+        assert_eq!(value["approx_objects"], serde_json::Value::Null);
+        assert_eq!(value["status"], "unknown");
+    }
+}
@@ -1,6 +1,6 @@
 use crate::branch::context::Context;
 use crate::branch::option::{BranchCommand, Command};
-use crate::branch::{create, current, drop, list, merge, rebase, rename, switch, wipe};
+use crate::branch::{create, current, diff, drop, list, merge, rebase, rename, switch, wipe};
 use crate::commands::{CommandResult, Options};
 use crate::connect::{Connection, Connector};
 
@@ -48,10 +48,11 @@ async fn run_branch_command1(
         Command::Current(current) => current::main(current, context, connection).await,
         Command::Create(create) => create::main(create, context, connection).await,
         Command::Drop(drop) => drop::main(drop, context, connection).await,
-        Command::List(list) => list::main(list, context, connection).await,
+        Command::List(list) => list::main(list, context, connection, options).await,
         Command::Rename(rename) => return rename::main(rename, context, connection, options).await,
         Command::Rebase(rebase) => rebase::main(rebase, context, connection, options).await,
         Command::Merge(merge) => merge::main(merge, context, connection, options).await,
+        Command::Diff(diff) => diff::main(diff, context, connection, options).await,
         unhandled => anyhow::bail!("unimplemented branch command '{:?}'", unhandled),
     }?;
 
@@ -1,7 +1,8 @@
-mod connections;
+pub mod connections;
 pub mod context;
 mod create;
 mod current;
+mod diff;
 mod drop;
 mod list;
 pub mod main;
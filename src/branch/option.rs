@@ -18,6 +18,7 @@ pub enum Command {
     Current(Current),
     Rebase(Rebase),
     Merge(Merge),
+    Diff(Diff),
     Rename(Rename),
     Drop(Drop),
     Wipe(Wipe),
@@ -120,7 +121,28 @@ pub struct Rename {
 
 /// List all branches.
 #[derive(clap::Args, Debug, Clone)]
-pub struct List {}
+pub struct List {
+    /// Output in JSON format.
+    #[arg(long)]
+    pub json: bool,
+
+    /// Sort branches by a column.
+    #[arg(long, value_enum, default_value = "name")]
+    pub sort: ListSort,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+#[value(rename_all = "kebab-case")]
+pub enum ListSort {
+    Name,
+    /// Most migrations first
+    Migrations,
+    /// Most objects first, by the estimate from table statistics. Stands
+    /// in for the branch size, which is not cheap to get
+    Objects,
+    /// Branches diverged from the default branch first
+    Status,
+}
 
 /// Creates a new branch that is based on the target branch, but also contains any new migrations
 /// on the current branch. Warning: data stored in current branch will be deleted.
@@ -145,6 +167,18 @@ pub struct Merge {
     pub no_apply: bool,
 }
 
+/// Shows schema changes and diverging migrations between two branches.
+#[derive(clap::Args, Clone, Debug)]
+pub struct Diff {
+    /// The branch to compare. If it's the only branch given, it is
+    /// compared to the current branch, showing what `branch merge`
+    /// would bring in. Otherwise, changes are shown relative to it.
+    pub branch: String,
+
+    /// The branch to compare to the first one.
+    pub other_branch: Option<String>,
+}
+
 /// Prints the current branch.
 #[derive(clap::Args, Clone, Debug)]
 pub struct Current {
@@ -10,6 +10,7 @@ use crate::non_interactive;
 use crate::options::{Command, Options};
 use crate::portable;
 use crate::print::style::Styler;
+use crate::schema;
 use crate::watch;
 use crate::{branch, cli};
 
@@ -39,10 +40,19 @@ pub fn main(options: &Options) -> Result<(), anyhow::Error> {
                     subcommand: M::Edit(params),
                     ..
                 }) if params.no_check => migrations::edit_no_check(&cmdopt, params),
+                Some(Migration {
+                    subcommand: M::Lint(params),
+                    ..
+                }) => migrations::lint_fs(&cmdopt, params),
                 Some(Migration {
                     subcommand: M::UpgradeCheck(params),
                     ..
                 }) => migrations::upgrade_check(&cmdopt, params),
+                // Reports connection errors as JSON, so connects by itself
+                Some(Migration {
+                    subcommand: M::Status(params),
+                    ..
+                }) if params.json => migrations::status_json_main(&cmdopt, params),
                 // Otherwise connect
                 _ => common_cmd(options, cmdopt, cmd),
             }
@@ -74,6 +84,10 @@ pub fn main(options: &Options) -> Result<(), anyhow::Error> {
             let cmdopt = init_command_opts(options)?;
             branch::branch_main(&cmdopt, c)
         }
+        Command::Schema(c) => {
+            let cmdopt = init_command_opts(options)?;
+            schema::schema_main(&cmdopt, c)
+        }
         Command::HashPassword(cmd) => {
             println!("{}", portable::password_hash(&cmd.password));
             Ok(())
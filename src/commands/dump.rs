@@ -1,4 +1,7 @@
+use std::collections::BTreeSet;
+use std::mem;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use anyhow::Context;
 use indicatif::{HumanBytes, ProgressBar};
@@ -18,6 +21,12 @@ use crate::platform::tmp_file_name;
 
 type Output = Box<dyn AsyncWrite + Unpin + Send>;
 
+/// Day in milliseconds, the unit of dump timestamps
+const DAY: u64 = 86_400_000;
+const DUMP_EXT: &str = ".dump";
+const DUMP_MAGIC: &[u8] = b"\xFF\xD8\x00\x00\xD8EDGEDB\x00DUMP\x00\
+    \x00\x00\x00\x00\x00\x00\x00\x01";
+
 pub struct Guard {
     filenames: Option<(PathBuf, PathBuf)>,
 }
@@ -47,19 +56,155 @@ impl Guard {
             ))
         }
     }
-    async fn commit(self) -> anyhow::Result<()> {
-        if let Some((tmp_filename, filename)) = self.filenames {
+    async fn commit(mut self) -> anyhow::Result<()> {
+        if let Some((tmp_filename, filename)) = self.filenames.take() {
             fs::rename(tmp_filename, filename).await?;
         }
         Ok(())
     }
 }
 
+impl Drop for Guard {
+    fn drop(&mut self) {
+        // not committed, so the temporary file is incomplete
+        if let Some((tmp_filename, _)) = self.filenames.take() {
+            std::fs::remove_file(tmp_filename).ok();
+        }
+    }
+}
+
+/// Dump file writer that optionally splits output into numbered chunks,
+/// each starting with its own copy of the dump header.
+///
+/// Chunks are written to temporary files which are renamed only when
+/// the whole dump is complete.
+struct DumpOutput {
+    filename: PathBuf,
+    split_size: Option<u64>,
+    header: Vec<u8>,
+    chunk: usize,
+    chunk_len: u64,
+    chunk_blocks: usize,
+    output: Output,
+    guard: Guard,
+    /// Guards of the finished chunks
+    finished: Vec<Guard>,
+}
+
+impl DumpOutput {
+    async fn open(filename: &Path, split_size: Option<u64>) -> anyhow::Result<DumpOutput> {
+        let path = if split_size.is_some() {
+            chunk_path(filename, 0)
+        } else {
+            filename.to_owned()
+        };
+        let (mut output, guard) = Guard::open(&path).await?;
+        output.write_all(DUMP_MAGIC).await?;
+        Ok(DumpOutput {
+            filename: filename.to_owned(),
+            split_size,
+            header: Vec::new(),
+            chunk: 0,
+            chunk_len: DUMP_MAGIC.len() as u64,
+            chunk_blocks: 0,
+            output,
+            guard,
+            finished: Vec::new(),
+        })
+    }
+    async fn write_header(&mut self, packet_header: &[u8], data: &[u8]) -> anyhow::Result<()> {
+        self.header.extend(packet_header);
+        self.header.extend(data);
+        self.output.write_all(&self.header).await?;
+        self.chunk_len += self.header.len() as u64;
+        Ok(())
+    }
+    async fn write_block(&mut self, packet_header: &[u8], data: &[u8]) -> anyhow::Result<()> {
+        let len = (packet_header.len() + data.len()) as u64;
+        if let Some(split_size) = self.split_size {
+            // at least one block is written into each chunk, even if it
+            // exceeds the limit on its own
+            if self.chunk_blocks > 0 && self.chunk_len + len > split_size {
+                self.next_chunk().await?;
+            }
+        }
+        self.output.write_all(packet_header).await?;
+        self.output.write_all(data).await?;
+        self.chunk_len += len;
+        self.chunk_blocks += 1;
+        Ok(())
+    }
+    async fn next_chunk(&mut self) -> anyhow::Result<()> {
+        self.chunk += 1;
+        let (mut output, guard) = Guard::open(&chunk_path(&self.filename, self.chunk)).await?;
+        output.write_all(DUMP_MAGIC).await?;
+        output.write_all(&self.header).await?;
+        let mut prev_output = mem::replace(&mut self.output, output);
+        let prev_guard = mem::replace(&mut self.guard, guard);
+        prev_output.flush().await?;
+        drop(prev_output);
+        self.finished.push(prev_guard);
+        self.chunk_len = (DUMP_MAGIC.len() + self.header.len()) as u64;
+        self.chunk_blocks = 0;
+        Ok(())
+    }
+    async fn commit(self) -> anyhow::Result<usize> {
+        let DumpOutput {
+            filename,
+            split_size,
+            mut output,
+            guard,
+            mut finished,
+            chunk,
+            ..
+        } = self;
+        output.flush().await?;
+        drop(output);
+        finished.push(guard);
+        for guard in finished {
+            guard.commit().await?;
+        }
+        if split_size.is_some() {
+            // Chunks left from an earlier, larger dump would be picked up
+            // by restore
+            for index in chunk + 1.. {
+                let stale = chunk_path(&filename, index);
+                match fs::remove_file(&stale).await {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+                    Err(e) => Err(e).with_context(|| format!("cannot remove {:?}", stale))?,
+                }
+            }
+        }
+        Ok(chunk + 1)
+    }
+}
+
+fn chunk_path(filename: &Path, index: usize) -> PathBuf {
+    let mut name = filename.as_os_str().to_owned();
+    name.push(format!(".{index:03}"));
+    PathBuf::from(name)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct KeepPolicy {
+    last: usize,
+    daily: usize,
+    weekly: usize,
+}
+
 pub async fn dump(
     cli: &mut Connection,
     general: &Options,
     options: &DumpOptions,
 ) -> Result<(), anyhow::Error> {
+    if let Some(dir) = &options.rotate {
+        return dump_rotate(cli, general, dir, options).await;
+    }
+    let path = options
+        .path
+        .as_ref()
+        .expect("path is required unless rotating");
     if options.all {
         if let Some(dformat) = options.format {
             if dformat != DumpFormat::Dir {
@@ -68,13 +213,143 @@ pub async fn dump(
         } else {
             anyhow::bail!("`--format=dir` is required when using `--all`");
         }
-        dump_all(cli, general, options.path.as_ref(), options.include_secrets).await
+        dump_all(cli, general, path, options.include_secrets).await
     } else {
         if options.format.is_some() {
             anyhow::bail!("`--format` is reserved for dump using `--all`");
         }
-        dump_db(cli, general, options.path.as_ref(), options.include_secrets).await
+        if options.split_size.is_some() && path.to_str() == Some("-") {
+            anyhow::bail!("`--split-size` cannot be used when writing to stdout");
+        }
+        dump_db(
+            cli,
+            general,
+            path,
+            options.include_secrets,
+            options.split_size,
+        )
+        .await
+    }
+}
+
+async fn dump_rotate(
+    cli: &mut Connection,
+    general: &Options,
+    dir: &Path,
+    options: &DumpOptions,
+) -> Result<(), anyhow::Error> {
+    if options.format.is_some() {
+        anyhow::bail!("`--format` is reserved for dump using `--all`");
+    }
+    fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("cannot create directory {:?}", dir))?;
+
+    let prefix = format!("{}-", urlencoding::encode(cli.database()));
+    let now = SystemTime::now();
+    let filename = dir.join(format!("{prefix}{}{DUMP_EXT}", format_timestamp(now)));
+    if fs::metadata(&filename).await.is_ok() {
+        anyhow::bail!("Dump {:?} already exists", filename);
+    }
+    dump_db(cli, general, &filename, options.include_secrets, None).await?;
+
+    if options.keep.is_none() && options.keep_daily.is_none() && options.keep_weekly.is_none() {
+        return Ok(());
+    }
+    let policy = KeepPolicy {
+        last: options.keep.unwrap_or(0),
+        daily: options.keep_daily.unwrap_or(0),
+        weekly: options.keep_weekly.unwrap_or(0),
+    };
+    let mut dumps = Vec::new();
+    let mut dir_list = fs::read_dir(dir).await?;
+    while let Some(entry) = dir_list.next_entry().await? {
+        let path = entry.path();
+        let timestamp = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix(&prefix[..]))
+            .and_then(|n| n.strip_suffix(DUMP_EXT))
+            .and_then(parse_timestamp);
+        if let Some(timestamp) = timestamp {
+            dumps.push((path, timestamp));
+        }
+    }
+    for path in dumps_to_prune(dumps, &policy) {
+        eprintln!("Removing old dump {}", path.display());
+        fs::remove_file(&path)
+            .await
+            .with_context(|| format!("cannot remove {:?}", path))?;
+    }
+    Ok(())
+}
+
+/// Formats timestamp as `YYYYMMDDTHHMMSS.mmmZ`, which is safe to use in
+/// filenames on all platforms and sorts chronologically. Milliseconds keep
+/// names of dumps made within the same second distinct.
+fn format_timestamp(time: SystemTime) -> String {
+    humantime::format_rfc3339_millis(time)
+        .to_string()
+        .replace(['-', ':'], "")
+}
+
+/// Parses timestamps written by `format_timestamp` into unix milliseconds.
+/// Timestamps without milliseconds, written by earlier versions, are
+/// accepted too.
+fn parse_timestamp(value: &str) -> Option<u64> {
+    let b = value.as_bytes();
+    let millis = match b.len() {
+        16 => "",
+        20 if b[15] == b'.' => &value[15..19],
+        _ => return None,
+    };
+    if b[8] != b'T' || b[b.len() - 1] != b'Z' {
+        return None;
+    }
+    let rfc3339 = format!(
+        "{}-{}-{}T{}:{}:{}{}Z",
+        &value[..4],
+        &value[4..6],
+        &value[6..8],
+        &value[9..11],
+        &value[11..13],
+        &value[13..15],
+        millis,
+    );
+    let time = humantime::parse_rfc3339(&rfc3339).ok()?;
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis() as u64)
+}
+
+/// Returns dumps that are not retained by any of the keep rules. The newest
+/// dump (the one just written) is never pruned.
+///
+/// Timestamps are unix milliseconds. Daily and weekly rules retain the latest dump
+/// of each period, weeks start on Monday.
+fn dumps_to_prune(mut dumps: Vec<(PathBuf, u64)>, policy: &KeepPolicy) -> Vec<PathBuf> {
+    dumps.sort_by(|(_, a), (_, b)| b.cmp(a));
+    let mut keep = BTreeSet::new();
+    keep.extend(0..policy.last.max(1).min(dumps.len()));
+    let mut days = BTreeSet::new();
+    let mut weeks = BTreeSet::new();
+    for (idx, (_, timestamp)) in dumps.iter().enumerate() {
+        let day = timestamp / DAY;
+        // 1970-01-01 is Thursday
+        let week = (day + 3) / 7;
+        if days.len() < policy.daily && days.insert(day) {
+            keep.insert(idx);
+        }
+        if weeks.len() < policy.weekly && weeks.insert(week) {
+            keep.insert(idx);
+        }
     }
+    dumps
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| !keep.contains(idx))
+        .map(|(_, (path, _))| path)
+        .collect()
 }
 
 async fn dump_db(
@@ -82,6 +357,7 @@ async fn dump_db(
     _options: &Options,
     filename: &Path,
     mut include_secrets: bool,
+    split_size: Option<u64>,
 ) -> Result<(), anyhow::Error> {
     if cli.get_version().await?.specific() < "4.0-alpha.2".parse().unwrap() {
         include_secrets = false;
@@ -90,13 +366,7 @@ async fn dump_db(
     let dbname = cli.database().to_string();
     eprintln!("Starting dump for {dbname}...");
 
-    let (mut output, guard) = Guard::open(filename).await?;
-    output
-        .write_all(
-            b"\xFF\xD8\x00\x00\xD8EDGEDB\x00DUMP\x00\
-          \x00\x00\x00\x00\x00\x00\x00\x01",
-        )
-        .await?;
+    let mut output = DumpOutput::open(filename, split_size).await?;
 
     let (header, mut blocks) = cli.dump(include_secrets).await?;
 
@@ -108,8 +378,7 @@ async fn dump_db(
     header_buf.push(b'H');
     header_buf.extend(&sha1::Sha1::new_with_prefix(&header.data).finalize()[..]);
     header_buf.extend(&(header.data.len() as u32).to_be_bytes()[..]);
-    output.write_all(&header_buf).await?;
-    output.write_all(&header.data).await?;
+    output.write_header(&header_buf, &header.data).await?;
 
     let bar = ProgressBar::new_spinner();
     let mut processed = 0;
@@ -131,12 +400,16 @@ async fn dump_db(
         header_buf.push(b'D');
         header_buf.extend(&sha1::Sha1::new_with_prefix(&packet.data).finalize()[..]);
         header_buf.extend(&(packet_length as u32).to_be_bytes()[..]);
-        output.write_all(&header_buf).await?;
-        output.write_all(&packet.data).await?;
+        output.write_block(&header_buf, &packet.data).await?;
     }
-    guard.commit().await?;
+    let chunks = output.commit().await?;
+    let files = if split_size.is_some() {
+        format!(" in {chunks} files")
+    } else {
+        String::new()
+    };
     bar.abandon_with_message(format!(
-        "Finished dump for {dbname}. Total size: {}",
+        "Finished dump for {dbname}. Total size: {}{files}",
         HumanBytes(processed as u64)
     ));
     Ok(())
@@ -174,7 +447,7 @@ pub async fn dump_all(
         match conn_params.branch(database)?.connect().await {
             Ok(mut db_conn) => {
                 let filename = dir.join(&(urlencoding::encode(database) + ".dump")[..]);
-                dump_db(&mut db_conn, options, &filename, include_secrets).await?;
+                dump_db(&mut db_conn, options, &filename, include_secrets, None).await?;
             }
             Err(err) => {
                 if let Some(e) = err.downcast_ref::<edgedb_errors::Error>() {
@@ -190,3 +463,116 @@ pub async fn dump_all(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    use super::{dumps_to_prune, format_timestamp, parse_timestamp, KeepPolicy, DAY};
+
+    fn dumps(timestamps: &[u64]) -> Vec<(PathBuf, u64)> {
+        timestamps
+            .iter()
+            .map(|t| (PathBuf::from(t.to_string()), *t))
+            .collect()
+    }
+
+    fn pruned(timestamps: &[u64], policy: KeepPolicy) -> Vec<String> {
+        dumps_to_prune(dumps(timestamps), &policy)
+            .into_iter()
+            .map(|p| p.display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn timestamp_roundtrip() {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_042);
+        assert_eq!(format_timestamp(time), "20231114T221320.042Z");
+        assert_eq!(
+            parse_timestamp("20231114T221320.042Z"),
+            Some(1_700_000_000_042)
+        );
+        assert_eq!(parse_timestamp("20231114T221320Z"), Some(1_700_000_000_000));
+        assert_eq!(parse_timestamp("2023-11-14"), None);
+        assert_eq!(parse_timestamp("20231114X221320Z"), None);
+        assert_eq!(parse_timestamp("20231114T221320,042Z"), None);
+    }
+
+    #[test]
+    fn same_second() {
+        let first = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_100);
+        let second = first + Duration::from_millis(300);
+        let (a, b) = (format_timestamp(first), format_timestamp(second));
+        assert_ne!(a, b);
+        assert!(a < b);
+        // The earlier of the two is rotated out
+        let stamps = [parse_timestamp(&a).unwrap(), parse_timestamp(&b).unwrap()];
+        let policy = KeepPolicy {
+            last: 1,
+            ..Default::default()
+        };
+        assert_eq!(pruned(&stamps, policy), vec![stamps[0].to_string()]);
+    }
+
+    #[test]
+    fn keep_last() {
+        let policy = KeepPolicy {
+            last: 2,
+            ..Default::default()
+        };
+        assert_eq!(pruned(&[1, 4, 2, 3], policy), vec!["2", "1"]);
+        assert_eq!(pruned(&[1], policy), Vec::<String>::new());
+    }
+
+    #[test]
+    fn keep_newest() {
+        assert_eq!(pruned(&[1, 3, 2], KeepPolicy::default()), vec!["2", "1"]);
+    }
+
+    #[test]
+    fn keep_daily_and_weekly() {
+        // 1970-01-05 is Monday
+        let monday = 4 * DAY;
+        let stamps = [
+            monday + 10,
+            monday + 20,
+            monday + DAY + 10,
+            monday + 7 * DAY + 10,
+            monday + 7 * DAY + 20,
+        ];
+        let policy = KeepPolicy {
+            daily: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            pruned(&stamps, policy),
+            vec![
+                (monday + 7 * DAY + 10).to_string(),
+                (monday + 20).to_string(),
+                (monday + 10).to_string(),
+            ]
+        );
+        let policy = KeepPolicy {
+            weekly: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            pruned(&stamps, policy),
+            vec![
+                (monday + 7 * DAY + 10).to_string(),
+                (monday + 20).to_string(),
+                (monday + 10).to_string(),
+            ]
+        );
+        let policy = KeepPolicy {
+            last: 2,
+            daily: 1,
+            weekly: 2,
+        };
+        assert_eq!(
+            pruned(&stamps, policy),
+            vec![(monday + 20).to_string(), (monday + 10).to_string()]
+        );
+    }
+}
@@ -118,9 +118,21 @@ pub async fn common(
             MigrationCmd::Status(params) => {
                 migrations::status(cli, options, params).await?;
             }
+            MigrationCmd::Diff(params) => {
+                migrations::diff(cli, options, params).await?;
+            }
             MigrationCmd::Log(params) => {
                 migrations::log(cli, options, params).await?;
             }
+            MigrationCmd::Lint(params) => {
+                migrations::lint(options, params).await?;
+            }
+            MigrationCmd::Revert(params) => {
+                migrations::revert(cli, options, params).await?;
+            }
+            MigrationCmd::Test(params) => {
+                migrations::test(cli, options, params).await?;
+            }
             MigrationCmd::Edit(params) => {
                 migrations::edit(cli, options, params).await?;
             }
@@ -393,13 +393,33 @@ pub struct Dump {
 
     /// Path to file write dump to (or directory if `--all` is specified).
     /// Use dash `-` to write to stdout (latter does not work in `--all` mode)
-    #[arg(value_hint=ValueHint::AnyPath)]
-    pub path: PathBuf,
+    #[arg(value_hint=ValueHint::AnyPath, required_unless_present="rotate")]
+    pub path: Option<PathBuf>,
     /// Dump all databases and server configuration. `path` is a directory
     /// in this case
     #[arg(long)]
     pub all: bool,
 
+    /// Write dump into `<branch>-<timestamp>.dump` file in the specified
+    /// directory and remove older dumps according to `--keep*` options
+    #[arg(long, value_hint=ValueHint::DirPath)]
+    #[arg(conflicts_with_all=["path", "all"])]
+    pub rotate: Option<PathBuf>,
+
+    /// Number of most recent dumps to keep when rotating
+    #[arg(long, requires = "rotate", value_parser=parse_keep)]
+    pub keep: Option<usize>,
+
+    /// Keep the latest dump of each of this many most recent days when
+    /// rotating
+    #[arg(long, requires = "rotate", value_parser=parse_keep)]
+    pub keep_daily: Option<usize>,
+
+    /// Keep the latest dump of each of this many most recent weeks when
+    /// rotating
+    #[arg(long, requires = "rotate", value_parser=parse_keep)]
+    pub keep_weekly: Option<usize>,
+
     /// Include secret configuration variables in the dump
     #[arg(long)]
     pub include_secrets: bool,
@@ -408,6 +428,12 @@ pub struct Dump {
     /// For `--all`, only `--format=dir` is required.
     #[arg(long, value_enum)]
     pub format: Option<DumpFormat>,
+
+    /// Split dump into files of at most this size (e.g. `512M` or `1G`)
+    /// named `<path>.000`, `<path>.001` and so on
+    #[arg(long, value_name="size", value_parser=parse_size)]
+    #[arg(conflicts_with_all=["all", "rotate"])]
+    pub split_size: Option<u64>,
 }
 
 #[derive(clap::Args, Clone, Debug)]
@@ -421,7 +447,8 @@ pub struct Restore {
     pub conn: Option<ConnectionOptions>,
 
     /// Path to file (or directory in case of `--all`) to read dump from.
-    /// Use dash `-` to read from stdin
+    /// Use dash `-` to read from stdin. For dumps created with
+    /// `--split-size` specify the first chunk (`<path>.000`) or a glob
     #[arg(value_hint=ValueHint::AnyPath)]
     pub path: PathBuf,
 
@@ -433,6 +460,15 @@ pub struct Restore {
     /// Verbose output
     #[arg(long, short = 'v')]
     pub verbose: bool,
+
+    /// Do not display restore progress, only errors
+    #[arg(long, conflicts_with = "json_progress")]
+    pub quiet: bool,
+
+    /// Report progress as JSON lines on stderr instead of a progress bar.
+    /// Useful for CI logs
+    #[arg(long)]
+    pub json_progress: bool,
 }
 
 #[derive(clap::Args, Clone, Debug)]
@@ -662,6 +698,34 @@ impl SettingBool {
     }
 }
 
+fn parse_size(s: &str) -> Result<u64, String> {
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(digits);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("`{s}` is not a valid size"))?;
+    let multiplier: u64 = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => return Err(format!("unknown unit in `{s}`, expected one of K, M, G, T")),
+    };
+    number
+        .checked_mul(multiplier)
+        .filter(|&size| size > 0)
+        .ok_or_else(|| format!("`{s}` is not a valid size"))
+}
+
+fn parse_keep(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) => Err("at least one dump must be kept".into()),
+        Ok(n) => Ok(n),
+        Err(_) => Err(format!("`{s}` is not a valid number")),
+    }
+}
+
 impl std::str::FromStr for DumpFormat {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<DumpFormat, anyhow::Error> {
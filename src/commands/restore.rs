@@ -2,15 +2,16 @@ use std::collections::BTreeSet;
 use std::convert::TryInto;
 use std::ffi::OsString;
 use std::future::Future;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::str;
 use std::task::{Context, Poll};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::Context as _;
 use bytes::{Bytes, BytesMut};
 use fn_error_context::context;
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
 use tokio::fs;
 use tokio::io::{self, AsyncRead, AsyncReadExt};
 use tokio_stream::Stream;
@@ -28,6 +29,10 @@ use crate::statement::{read_statement, EndOfFile};
 type Input = Box<dyn AsyncRead + Unpin + Send>;
 
 const MAX_SUPPORTED_DUMP_VER: i64 = 1;
+const FILE_HEADER_LEN: u64 = 17 + 8;
+const PACKET_HEADER_LEN: usize = 1 + 20 + 4;
+const JSON_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+const DUMP_MAGIC: &[u8] = b"\xFF\xD8\x00\x00\xD8EDGEDB\x00DUMP\x00";
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PacketType {
@@ -36,8 +41,163 @@ pub enum PacketType {
 }
 
 pub struct Packets<'a> {
-    input: &'a mut Input,
+    input: Input,
     buf: BytesMut,
+    header: Bytes,
+    chunks: std::vec::IntoIter<PathBuf>,
+    progress: &'a mut Progress,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProgressMode {
+    Bar,
+    Json,
+    Quiet,
+}
+
+pub struct Progress {
+    mode: ProgressMode,
+    bar: ProgressBar,
+    total: Option<u64>,
+    bytes: u64,
+    blocks: u64,
+    started: Instant,
+    last_report: Instant,
+}
+
+#[derive(serde::Serialize)]
+struct ProgressJson {
+    event: &'static str,
+    bytes: u64,
+    total_bytes: Option<u64>,
+    blocks: u64,
+    elapsed_secs: f64,
+    bytes_per_sec: u64,
+    eta_secs: Option<u64>,
+}
+
+impl ProgressMode {
+    fn from_params(params: &RestoreCmd) -> ProgressMode {
+        if params.json_progress {
+            ProgressMode::Json
+        } else if params.quiet {
+            ProgressMode::Quiet
+        } else {
+            ProgressMode::Bar
+        }
+    }
+}
+
+impl Progress {
+    fn new(mode: ProgressMode, total: Option<u64>) -> Progress {
+        let bar = match (mode, total) {
+            (ProgressMode::Bar, Some(total)) => {
+                let bar = ProgressBar::new(total);
+                bar.set_style(
+                    ProgressStyle::default_bar()
+                        .template(
+                            "{elapsed_precise} [{bar}] \
+                            {bytes:>7.dim}/{total_bytes:7} \
+                            {binary_bytes_per_sec:.dim} | ETA: {eta} | {msg}",
+                        )
+                        .expect("template is ok")
+                        .progress_chars("=> "),
+                );
+                bar
+            }
+            (ProgressMode::Bar, None) => {
+                let bar = ProgressBar::new_spinner();
+                bar.set_style(
+                    ProgressStyle::default_spinner()
+                        .template(
+                            "{spinner} {elapsed_precise} {bytes:>7.dim} \
+                            {binary_bytes_per_sec:.dim} | {msg}",
+                        )
+                        .expect("template is ok"),
+                );
+                bar
+            }
+            (ProgressMode::Json | ProgressMode::Quiet, _) => ProgressBar::hidden(),
+        };
+        let now = Instant::now();
+        let mut progress = Progress {
+            mode,
+            bar,
+            total,
+            bytes: 0,
+            blocks: 0,
+            started: now,
+            last_report: now,
+        };
+        progress.add_bytes(FILE_HEADER_LEN);
+        progress
+    }
+    fn add_bytes(&mut self, len: u64) {
+        self.bytes += len;
+        self.bar.inc(len);
+    }
+    fn block(&mut self, len: usize) {
+        self.blocks += 1;
+        self.add_bytes((PACKET_HEADER_LEN + len) as u64);
+        self.bar.set_message(format!("{} blocks", self.blocks));
+        if self.mode == ProgressMode::Json && self.last_report.elapsed() >= JSON_PROGRESS_INTERVAL {
+            self.last_report = Instant::now();
+            self.print_json("progress");
+        }
+    }
+    fn bytes_per_sec(&self) -> u64 {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            (self.bytes as f64 / elapsed) as u64
+        } else {
+            0
+        }
+    }
+    fn eta_secs(&self) -> Option<u64> {
+        let total = self.total?;
+        let rate = self.bytes_per_sec();
+        if rate == 0 {
+            return None;
+        }
+        Some(total.saturating_sub(self.bytes) / rate)
+    }
+    fn print_json(&self, event: &'static str) {
+        let data = ProgressJson {
+            event,
+            bytes: self.bytes,
+            total_bytes: self.total,
+            blocks: self.blocks,
+            elapsed_secs: self.started.elapsed().as_secs_f64(),
+            bytes_per_sec: self.bytes_per_sec(),
+            eta_secs: self.eta_secs(),
+        };
+        eprintln!(
+            "{}",
+            serde_json::to_string(&data).expect("progress is serializable")
+        );
+    }
+    fn finish(&self, dbname: &str) {
+        match self.mode {
+            ProgressMode::Bar => {
+                self.bar.finish_and_clear();
+                eprintln!(
+                    "Finished restore of {dbname}. Total size: {}, {} blocks \
+                     in {} ({}/s)",
+                    HumanBytes(self.bytes),
+                    self.blocks,
+                    humantime::format_duration(Duration::from_secs(
+                        self.started.elapsed().as_secs()
+                    )),
+                    HumanBytes(self.bytes_per_sec()),
+                );
+            }
+            ProgressMode::Json => self.print_json("finished"),
+            ProgressMode::Quiet => {}
+        }
+    }
+    fn abandon(&self) {
+        self.bar.abandon();
+    }
 }
 
 async fn read_packet(
@@ -45,9 +205,8 @@ async fn read_packet(
     buf: &mut BytesMut,
     expected: PacketType,
 ) -> Result<Option<Bytes>, anyhow::Error> {
-    const HEADER_LEN: usize = 1 + 20 + 4;
-    while buf.len() < HEADER_LEN {
-        buf.reserve(HEADER_LEN);
+    while buf.len() < PACKET_HEADER_LEN {
+        buf.reserve(PACKET_HEADER_LEN);
         let n = input
             .read_buf(buf)
             .await
@@ -75,10 +234,10 @@ async fn read_packet(
         ));
     }
     let len = u32::from_be_bytes(buf[1 + 20..][..4].try_into().unwrap()) as usize;
-    if buf.capacity() < HEADER_LEN + len {
-        buf.reserve(HEADER_LEN + len - buf.capacity());
+    if buf.capacity() < PACKET_HEADER_LEN + len {
+        buf.reserve(PACKET_HEADER_LEN + len - buf.capacity());
     }
-    while buf.len() < HEADER_LEN + len {
+    while buf.len() < PACKET_HEADER_LEN + len {
         let read = input
             .read_buf(buf)
             .await
@@ -89,19 +248,130 @@ async fn read_packet(
         }
     }
     Ok(Some(
-        buf.split_to(HEADER_LEN + len)
-            .split_off(HEADER_LEN)
+        buf.split_to(PACKET_HEADER_LEN + len)
+            .split_off(PACKET_HEADER_LEN)
             .freeze(),
     ))
 }
 
+async fn read_prelude(input: &mut Input, buf: &mut BytesMut) -> anyhow::Result<Bytes> {
+    let mut file_header = [0u8; FILE_HEADER_LEN as usize];
+    input
+        .read_exact(&mut file_header)
+        .await
+        .context("Cannot read header")?;
+    if &file_header[..17] != DUMP_MAGIC {
+        anyhow::bail!("Incorrect header; file is not an EdgeDB dump");
+    }
+    let version = i64::from_be_bytes(file_header[17..].try_into().unwrap());
+    if version == 0 || version > MAX_SUPPORTED_DUMP_VER {
+        anyhow::bail!("Unsupported dump version {}", version);
+    }
+    read_packet(input, buf, PacketType::Header)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Dump is empty"))
+}
+
 impl Packets<'_> {
-    async fn next(&mut self) -> Option<Result<Bytes, Error>> {
-        read_packet(self.input, &mut self.buf, PacketType::Block)
+    async fn next_chunk(&mut self) -> anyhow::Result<bool> {
+        let Some(path) = self.chunks.next() else {
+            return Ok(false);
+        };
+        let file_ctx = || format!("Failed to read dump {}", path.display());
+        let file = fs::File::open(&path).await.with_context(file_ctx)?;
+        let mut input = Box::new(file) as Input;
+        let header = read_prelude(&mut input, &mut self.buf)
             .await
-            .map_err(UserError::with_source_ref)
-            .transpose()
+            .with_context(file_ctx)?;
+        if header != self.header {
+            anyhow::bail!("{} is a chunk of a different dump", path.display());
+        }
+        self.progress
+            .add_bytes(FILE_HEADER_LEN + (PACKET_HEADER_LEN + header.len()) as u64);
+        self.input = input;
+        Ok(true)
+    }
+    async fn next(&mut self) -> Option<Result<Bytes, Error>> {
+        loop {
+            match read_packet(&mut self.input, &mut self.buf, PacketType::Block).await {
+                Ok(Some(data)) => {
+                    self.progress.block(data.len());
+                    return Some(Ok(data));
+                }
+                Ok(None) => match self.next_chunk().await {
+                    Ok(true) => continue,
+                    Ok(false) => return None,
+                    Err(e) => return Some(Err(UserError::with_source_ref(e))),
+                },
+                Err(e) => return Some(Err(UserError::with_source_ref(e))),
+            }
+        }
+    }
+}
+
+fn chunk_index(path: &Path) -> Option<usize> {
+    let ext = path.extension()?.to_str()?;
+    if ext.len() >= 3 && ext.bytes().all(|b| b.is_ascii_digit()) {
+        ext.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Returns files to restore from. Dumps split by `--split-size` are
+/// specified either by the first chunk (`<path>.000`) or by a glob.
+async fn dump_files(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if let Some(pattern) = path.to_str().filter(|p| p.contains(['*', '?', '['])) {
+        let files = glob::glob(pattern)
+            .with_context(|| format!("invalid glob {:?}", pattern))?
+            .collect::<Result<Vec<_>, _>>()?;
+        if files.is_empty() {
+            anyhow::bail!("no dump files match {:?}", pattern);
+        }
+        return order_chunks(files)
+            .with_context(|| format!("invalid dump chunks matching {:?}", pattern));
+    }
+    let mut files = vec![path.to_owned()];
+    if chunk_index(path) == Some(0) {
+        for index in 1.. {
+            let chunk = path.with_extension(format!("{index:03}"));
+            if fs::metadata(&chunk).await.is_err() {
+                break;
+            }
+            files.push(chunk);
+        }
     }
+    Ok(files)
+}
+
+/// Orders chunks written by `dump --split-size` by their number. Files
+/// without a chunk number, like leftover temporary files, are skipped.
+fn order_chunks(files: Vec<PathBuf>) -> anyhow::Result<Vec<PathBuf>> {
+    let mut chunks = files
+        .into_iter()
+        .filter_map(|path| Some((chunk_index(&path)?, path)))
+        .collect::<Vec<_>>();
+    chunks.sort();
+    let Some((_, first)) = chunks.first() else {
+        anyhow::bail!("no numbered chunks (`<path>.000`, `<path>.001`, ...) found");
+    };
+    let base = first.with_extension("");
+    for (expected, (index, path)) in chunks.iter().enumerate() {
+        if path.with_extension("") != base {
+            anyhow::bail!(
+                "{} and {} are chunks of different dumps",
+                first.display(),
+                path.display()
+            );
+        }
+        if *index != expected {
+            anyhow::bail!(
+                "chunk {} is missing",
+                base.with_extension(format!("{expected:03}")).display()
+            );
+        }
+    }
+    Ok(chunks.into_iter().map(|(_, path)| path).collect())
 }
 
 impl Stream for Packets<'_> {
@@ -152,13 +422,15 @@ async fn restore_db<'x>(
     _options: &Options,
     params: &RestoreCmd,
 ) -> Result<(), anyhow::Error> {
-    use PacketType::*;
     let RestoreCmd {
         path: ref filename,
         all: _,
         verbose: _,
         conn: _,
+        quiet: _,
+        json_progress: _,
     } = *params;
+    let mode = ProgressMode::from_params(params);
     if is_non_empty_db(cli).await? {
         return Err(anyhow::anyhow!(
             "\
@@ -166,49 +438,62 @@ async fn restore_db<'x>(
         ));
     }
 
-    let file_ctx = &|| format!("Failed to read dump {}", filename.display());
-    let mut input = if filename.to_str() == Some("-") {
-        Box::new(io::stdin()) as Input
+    let (mut input, file_size, chunks) = if filename.to_str() == Some("-") {
+        (Box::new(io::stdin()) as Input, None, Vec::new())
     } else {
-        let file = fs::File::open(filename).await.with_context(file_ctx)?;
-        let file_size = file.metadata().await?.len();
-        eprintln!(
-            "\nRestoring database from file `{}`. Total size: {:.02} MB",
-            filename.display(),
-            file_size as f64 / 1048576.0
-        );
-        Box::new(file) as Input
+        let mut files = dump_files(filename).await?;
+        let mut file_size = 0;
+        for path in &files {
+            file_size += fs::metadata(path)
+                .await
+                .with_context(|| format!("Failed to read dump {}", path.display()))?
+                .len();
+        }
+        if mode == ProgressMode::Bar {
+            let source = if files.len() > 1 {
+                format!("{} files starting with", files.len())
+            } else {
+                "file".into()
+            };
+            eprintln!(
+                "\nRestoring database from {source} `{}`. Total size: {:.02} MB",
+                files[0].display(),
+                file_size as f64 / 1048576.0
+            );
+        }
+        let first = files.remove(0);
+        let file = fs::File::open(&first)
+            .await
+            .with_context(|| format!("Failed to read dump {}", first.display()))?;
+        (Box::new(file) as Input, Some(file_size), files)
     };
-    let mut buf = [0u8; 17 + 8];
-    input
-        .read_exact(&mut buf)
-        .await
-        .context("Cannot read header")
-        .with_context(file_ctx)?;
-    if &buf[..17] != b"\xFF\xD8\x00\x00\xD8EDGEDB\x00DUMP\x00" {
-        Err(anyhow::anyhow!(
-            "Incorrect header; file is not an EdgeDB dump"
-        ))
-        .with_context(file_ctx)?
-    }
-    let version = i64::from_be_bytes(buf[17..].try_into().unwrap());
-    if version == 0 || version > MAX_SUPPORTED_DUMP_VER {
-        Err(anyhow::anyhow!("Unsupported dump version {}", version)).with_context(file_ctx)?
-    }
+    let file_ctx = &|| format!("Failed to read dump {}", filename.display());
     let mut buf = BytesMut::with_capacity(65536);
-    let header = read_packet(&mut input, &mut buf, Header)
+    let header = read_prelude(&mut input, &mut buf)
         .await
-        .with_context(file_ctx)?
-        .ok_or_else(|| anyhow::anyhow!("Dump is empty"))
         .with_context(file_ctx)?;
-    cli.restore(
-        header,
-        Packets {
-            input: &mut input,
-            buf,
-        },
-    )
-    .await?;
+    let mut progress = Progress::new(mode, file_size);
+    progress.add_bytes((PACKET_HEADER_LEN + header.len()) as u64);
+    let dbname = cli.database().to_string();
+    let result = cli
+        .restore(
+            header.clone(),
+            Packets {
+                input,
+                buf,
+                header,
+                chunks: chunks.into_iter(),
+                progress: &mut progress,
+            },
+        )
+        .await;
+    match result {
+        Ok(()) => progress.finish(&dbname),
+        Err(e) => {
+            progress.abandon();
+            return Err(e.into());
+        }
+    }
     Ok(())
 }
 
@@ -287,3 +572,33 @@ pub async fn restore_all<'x>(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::order_chunks;
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn chunks_in_order() {
+        let files = paths(&["x.dump.002", "x.dump.000.tmp", "x.dump.000", "x.dump.001"]);
+        assert_eq!(
+            order_chunks(files).unwrap(),
+            paths(&["x.dump.000", "x.dump.001", "x.dump.002"])
+        );
+    }
+
+    #[test]
+    fn bad_chunks() {
+        let err = order_chunks(paths(&["x.dump.000", "x.dump.002"])).unwrap_err();
+        assert_eq!(err.to_string(), "chunk x.dump.001 is missing");
+        let err = order_chunks(paths(&["x.dump.001"])).unwrap_err();
+        assert_eq!(err.to_string(), "chunk x.dump.000 is missing");
+        assert!(order_chunks(paths(&["x.dump.000", "y.dump.001"])).is_err());
+        assert!(order_chunks(paths(&["x.dump.000.tmp"])).is_err());
+    }
+}
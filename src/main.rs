@@ -42,6 +42,7 @@ mod process;
 mod prompt;
 mod question;
 mod repl;
+mod schema;
 mod statement;
 mod table;
 mod tty_password;
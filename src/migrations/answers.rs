@@ -0,0 +1,161 @@
+use std::fs;
+use std::path::Path;
+
+use fn_error_context::context;
+use regex::Regex;
+
+use crate::migrations::create::{Proposal, RequiredUserInput};
+
+/// Answers file format, either TOML or JSON (by file extension):
+///
+/// ```toml
+/// [[answer]]
+/// statement = "ALTER TYPE default::User"
+/// apply = true
+///
+/// [[answer]]
+/// placeholder = "cast_expr"
+/// expression = "<int64>.age"
+/// ```
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct SrcAnswers {
+    #[serde(default)]
+    answer: Vec<SrcAnswer>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct SrcAnswer {
+    statement: Option<String>,
+    placeholder: Option<String>,
+    apply: Option<bool>,
+    expression: Option<String>,
+}
+
+#[derive(Debug)]
+struct Answer {
+    /// Matches either proposed statements or the prompt
+    statement: Option<Regex>,
+    placeholder: Option<String>,
+    apply: Option<bool>,
+    expression: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Answers {
+    answers: Vec<Answer>,
+}
+
+impl Answers {
+    #[context("error reading answers file {}", path.display())]
+    pub fn read(path: &Path) -> anyhow::Result<Answers> {
+        let text = fs::read_to_string(path)?;
+        let src: SrcAnswers = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&text)?,
+            _ => toml::from_str(&text)?,
+        };
+        let answers = src
+            .answer
+            .into_iter()
+            .enumerate()
+            .map(|(idx, a)| {
+                if a.apply.is_none() && a.expression.is_none() {
+                    anyhow::bail!("answer #{} must contain `apply` or `expression`", idx + 1);
+                }
+                if a.expression.is_some() != a.placeholder.is_some() {
+                    anyhow::bail!(
+                        "answer #{}: `expression` and `placeholder` must be used together",
+                        idx + 1
+                    );
+                }
+                Ok(Answer {
+                    statement: a.statement.as_deref().map(Regex::new).transpose()?,
+                    placeholder: a.placeholder,
+                    apply: a.apply,
+                    expression: a.expression,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Answers { answers })
+    }
+
+    /// Returns whether the first matching answer accepts the proposal
+    pub fn decision(&self, proposal: &Proposal) -> Option<bool> {
+        self.answers
+            .iter()
+            .filter(|a| a.matches(proposal))
+            .find_map(|a| a.apply)
+    }
+
+    pub fn expression(&self, proposal: &Proposal, input: &RequiredUserInput) -> Option<&str> {
+        self.answers
+            .iter()
+            .filter(|a| a.placeholder.as_deref() == Some(&input.placeholder[..]))
+            .filter(|a| a.matches(proposal))
+            .find_map(|a| a.expression.as_deref())
+    }
+}
+
+impl Answer {
+    fn matches(&self, proposal: &Proposal) -> bool {
+        let Some(re) = &self.statement else {
+            return true;
+        };
+        proposal.statements.iter().any(|s| re.is_match(&s.text))
+            || proposal.prompt.as_deref().map_or(false, |p| re.is_match(p))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use super::Answers;
+    use crate::migrations::create::Proposal;
+
+    #[test]
+    fn decisions() {
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        file.write_all(
+            br#"
+                [[answer]]
+                statement = "DROP PROPERTY"
+                apply = false
+
+                [[answer]]
+                statement = "age"
+                placeholder = "cast_expr"
+                expression = "<int64>.age"
+
+                [[answer]]
+                apply = true
+            "#,
+        )
+        .unwrap();
+        let answers = Answers::read(file.path()).unwrap();
+        let proposal: Proposal = serde_json::from_str(
+            r#"{
+                "prompt_id": null,
+                "statements": [{"text": "ALTER TYPE User { ALTER PROPERTY age \\(cast_expr) };"}],
+                "confidence": 0.5,
+                "required_user_input": [{"placeholder": "cast_expr", "prompt": "cast"}]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(answers.decision(&proposal), Some(true));
+        assert_eq!(
+            answers.expression(&proposal, &proposal.required_user_input[0]),
+            Some("<int64>.age")
+        );
+        let proposal: Proposal = serde_json::from_str(
+            r#"{
+                "prompt_id": null,
+                "statements": [{"text": "ALTER TYPE User { DROP PROPERTY name };"}],
+                "confidence": 0.5
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(answers.decision(&proposal), Some(false));
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 use crate::migrations::options::MigrationConfig;
 use crate::portable::config;
@@ -15,6 +15,9 @@ pub struct Context {
     /// a non-existing entry in edgedb.toml will be taken as "stable".
     pub edgedb_version: Option<Query>,
 
+    /// Settings from the `[migrations]` section of edgedb.toml.
+    pub migrations: config::Migrations,
+
     pub quiet: bool,
 }
 
@@ -24,12 +27,14 @@ impl Context {
         quiet: bool,
     ) -> anyhow::Result<Context> {
         let mut edgedb_version = None;
+        let mut migrations = config::Migrations::default();
         let schema_dir = if let Some(schema_dir) = &cfg.schema_dir {
             schema_dir.clone()
         } else if let Some(cfg_dir) = get_project_dir(None, true).await? {
             let config_path = cfg_dir.join("edgedb.toml");
             let config = config::read(&config_path)?;
             edgedb_version = Some(config.edgedb.server_version);
+            migrations = config.migrations;
             config.project.schema_dir
         } else {
             let default_dir: PathBuf = "./dbschema".into();
@@ -42,18 +47,15 @@ impl Context {
         Ok(Context {
             schema_dir,
             edgedb_version,
+            migrations,
             quiet,
         })
     }
-    pub fn for_watch(project_dir: &Path) -> anyhow::Result<Context> {
-        let config_path = project_dir.join("edgedb.toml");
-        let config = config::read(&config_path)?;
-        Context::for_project(&config)
-    }
     pub fn for_project(config: &config::Config) -> anyhow::Result<Context> {
         Ok(Context {
             schema_dir: config.project.schema_dir.clone(),
             edgedb_version: Some(config.edgedb.server_version.clone()),
+            migrations: config.migrations.clone(),
             quiet: false,
         })
     }
@@ -1,7 +1,9 @@
 use std::borrow::Cow;
 use std::collections::BTreeMap;
+use std::io::stdin;
 use std::path::{Path, PathBuf};
 use std::slice::Iter;
+use std::time::SystemTime;
 
 use anyhow::Context as _;
 use colorful::Colorful;
@@ -9,10 +11,12 @@ use edgedb_derive::Queryable;
 use edgedb_errors::{Error, InvalidSyntaxError, QueryError};
 use edgeql_parser::expr;
 use edgeql_parser::hash::Hasher;
+use edgeql_parser::helpers::quote_string;
 use edgeql_parser::schema_file::validate;
 use edgeql_parser::tokenizer::{Kind as TokenKind, Tokenizer};
 use fn_error_context::context;
 use immutable_chunkmap::set::SetM as Set;
+use is_terminal::IsTerminal;
 use once_cell::sync::OnceCell;
 use rustyline::error::ReadlineError;
 use serde::Deserialize;
@@ -26,12 +30,13 @@ use crate::commands::{ExitCode, Options};
 use crate::connect::Connection;
 use crate::error_display::print_query_error;
 use crate::highlight;
+use crate::migrations::answers::Answers;
 use crate::migrations::context::Context;
 use crate::migrations::dev_mode;
 use crate::migrations::edb::{execute, execute_if_connected, query_row};
 use crate::migrations::migration;
 use crate::migrations::options::CreateMigration;
-use crate::migrations::print_error::print_migration_error;
+use crate::migrations::print_error::{error_location, print_migration_error, ErrorLocation};
 use crate::migrations::prompt;
 use crate::migrations::source_map::{Builder, SourceMap};
 use crate::migrations::squash;
@@ -39,6 +44,7 @@ use crate::migrations::timeout;
 use crate::platform::tmp_file_name;
 use crate::print;
 use crate::print::style::Styler;
+use crate::prompt::spawn_editor;
 use crate::question;
 
 const SAFE_CONFIDENCE: f64 = 0.99999;
@@ -55,6 +61,7 @@ enum Choice {
     Yes,
     No,
     List,
+    Edit,
     Confirmed,
     Back,
     Split,
@@ -63,8 +70,8 @@ enum Choice {
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct RequiredUserInput {
-    placeholder: String,
-    prompt: String,
+    pub placeholder: String,
+    pub prompt: String,
     #[allow(dead_code)]
     old_type: Option<String>,
     old_type_is_object: Option<bool>,
@@ -112,6 +119,10 @@ pub trait MigrationToText<'a, T: Iterator<Item = &'a String> = std::iter::Once<&
     fn parent(&self) -> anyhow::Result<&str>;
     fn id(&self) -> anyhow::Result<&str>;
     fn statements(&'a self) -> T;
+    /// Comment lines written before the migration statement.
+    fn comments(&'a self) -> &'a [String] {
+        &[]
+    }
 }
 
 #[derive(Debug)]
@@ -119,6 +130,7 @@ pub struct FutureMigration {
     key: MigrationKey,
     parent: String,
     statements: Vec<String>,
+    comments: Vec<String>,
     id: OnceCell<String>,
 }
 
@@ -146,7 +158,10 @@ struct CantResolve;
 
 #[derive(Debug, thiserror::Error)]
 #[error("cannot proceed until .esdl files are fixed")]
-pub struct EsdlError;
+pub struct EsdlError {
+    pub error: Error,
+    pub location: Option<ErrorLocation>,
+}
 
 impl FutureMigration {
     fn new(key: MigrationKey, descr: CurrentMigration) -> Self {
@@ -154,6 +169,7 @@ impl FutureMigration {
             key,
             parent: descr.parent,
             statements: descr.confirmed,
+            comments: Vec::new(),
             id: OnceCell::new(),
         }
     }
@@ -162,9 +178,20 @@ impl FutureMigration {
             key,
             parent: parent.to_owned(),
             statements: Vec::new(),
+            comments: Vec::new(),
             id: OnceCell::new(),
         }
     }
+    /// Prepends `SET message` to the statements. Must be called before
+    /// the migration id is computed, since the message is part of the hash.
+    pub fn set_message(&mut self, message: &str) {
+        self.statements
+            .insert(0, format!("SET message := {};", quote_string(message)));
+        self.id = OnceCell::new();
+    }
+    pub fn set_comments(&mut self, comments: Vec<String>) {
+        self.comments = comments;
+    }
 }
 
 impl<'a> MigrationToText<'a, Iter<'a, String>> for FutureMigration {
@@ -198,6 +225,10 @@ impl<'a> MigrationToText<'a, Iter<'a, String>> for FutureMigration {
     fn statements(&'a self) -> Iter<'a, String> {
         self.statements.iter()
     }
+
+    fn comments(&'a self) -> &'a [String] {
+        &self.comments
+    }
 }
 
 #[context("could not read schema file {}", path.display())]
@@ -207,7 +238,7 @@ async fn read_schema_file(path: &Path) -> anyhow::Result<String> {
     Ok(data)
 }
 
-fn print_statements(statements: impl IntoIterator<Item = impl AsRef<str>>) {
+pub fn print_statements(statements: impl IntoIterator<Item = impl AsRef<str>>) {
     let mut buf: String = String::with_capacity(1024);
     let styler = Styler::dark_256();
     for statement in statements {
@@ -238,6 +269,11 @@ async fn choice(prompt: &str) -> anyhow::Result<Choice> {
         &["l", "list"],
         "List proposed DDL statements for the current prompt",
     );
+    q.option(
+        Edit,
+        &["e", "edit"],
+        "Edit proposed DDL statements in $EDITOR before applying them",
+    );
     q.option(
         Confirmed,
         &["c", "confirmed"],
@@ -258,7 +294,7 @@ async fn choice(prompt: &str) -> anyhow::Result<Choice> {
 }
 
 #[context("could not read schema in {}", ctx.schema_dir.display())]
-async fn gen_start_migration(ctx: &Context) -> anyhow::Result<(String, SourceMap<SourceName>)> {
+pub async fn gen_start_migration(ctx: &Context) -> anyhow::Result<(String, SourceMap<SourceName>)> {
     let mut bld = Builder::new();
     bld.add_lines(SourceName::Prefix, "START MIGRATION TO {");
     let mut dir = match fs::read_dir(&ctx.schema_dir).await {
@@ -275,7 +311,7 @@ async fn gen_start_migration(ctx: &Context) -> anyhow::Result<(String, SourceMap
         let fname = item.file_name();
         let lossy_name = fname.to_string_lossy();
         if !lossy_name.starts_with('.')
-            && lossy_name.ends_with(".esdl")
+            && (lossy_name.ends_with(".esdl") || lossy_name.ends_with(".gel"))
             && item.file_type().await?.is_file()
         {
             paths.push(item.path())
@@ -300,7 +336,8 @@ pub async fn execute_start_migration(ctx: &Context, cli: &mut Connection) -> any
         Ok(_) => Ok(()),
         Err(e) if e.is::<QueryError>() => {
             print_migration_error(&e, &source_map)?;
-            Err(EsdlError)?
+            let location = error_location(&e, &source_map);
+            Err(EsdlError { error: e, location })?
         }
         Err(e) => Err(e)?,
     }
@@ -406,7 +443,7 @@ pub async fn unsafe_populate(
     }
 }
 
-async fn apply_proposal(
+pub async fn apply_proposal(
     cli: &mut Connection,
     proposal: &Proposal,
     placeholders: &BTreeMap<String, String>,
@@ -493,13 +530,80 @@ async fn non_interactive_populate(
     }
 }
 
-async fn run_non_interactive(
+async fn answers_populate(
+    _ctx: &Context,
+    cli: &mut Connection,
+    answers: &Answers,
+    allow_unsafe: bool,
+) -> anyhow::Result<CurrentMigration> {
+    loop {
+        let data = query_row::<CurrentMigration>(cli, "DESCRIBE CURRENT MIGRATION AS JSON").await?;
+        if data.complete {
+            return Ok(data);
+        }
+        let Some(proposal) = &data.proposed else {
+            anyhow::bail!(
+                "EdgeDB could not resolve \
+                migration automatically. Please run in \
+                interactive mode to confirm changes."
+            );
+        };
+        let apply = match answers.decision(proposal) {
+            Some(apply) => apply,
+            None if proposal.confidence >= SAFE_CONFIDENCE || allow_unsafe => true,
+            None => {
+                eprintln!("EdgeDB intended to apply the following migration:");
+                for statement in &proposal.statements {
+                    for line in statement.text.lines() {
+                        eprintln!("    {}", line);
+                    }
+                }
+                anyhow::bail!(
+                    "No answer matches the proposed statements. Add an \
+                    answer with `apply` to the answers file, \
+                    or use `--allow-unsafe`"
+                );
+            }
+        };
+        if !apply {
+            execute(cli, "ALTER CURRENT MIGRATION REJECT PROPOSED").await?;
+            continue;
+        }
+        let mut placeholders = BTreeMap::new();
+        for input in &proposal.required_user_input {
+            let expr = match answers.expression(proposal, input) {
+                Some(expr) => Some(expr.to_string()),
+                None if allow_unsafe => make_default_expression(input),
+                None => None,
+            };
+            let Some(expr) = expr else {
+                eprintln!("Input required: {}", input.prompt);
+                anyhow::bail!(
+                    "No answer for placeholder {:?} in the answers file",
+                    input.placeholder
+                );
+            };
+            placeholders.insert(input.placeholder.clone(), expr);
+        }
+        if !apply_proposal(cli, proposal, &placeholders).await? {
+            anyhow::bail!(
+                "Proposed statements failed with the provided answers. \
+                Check expressions in the answers file."
+            );
+        }
+    }
+}
+
+pub async fn run_non_interactive(
     ctx: &Context,
     cli: &mut Connection,
     key: MigrationKey,
     options: &CreateMigration,
 ) -> anyhow::Result<FutureMigration> {
-    let descr = if options.allow_unsafe {
+    let descr = if let Some(path) = &options.answers {
+        let answers = Answers::read(path)?;
+        answers_populate(ctx, cli, &answers, options.allow_unsafe).await?
+    } else if options.allow_unsafe {
         unsafe_populate(ctx, cli).await?
     } else {
         non_interactive_populate(ctx, cli).await?
@@ -570,9 +674,9 @@ impl InteractiveMigration<'_> {
             .as_ref()
             .map(|op| cur_oper.contains(op))
             .unwrap_or(false);
-        let input;
+        let statements;
         if already_approved {
-            input = loop {
+            let input = loop {
                 println!("The following extra DDL statements will be applied:");
                 for statement in &proposal.statements {
                     for line in statement.text.lines() {
@@ -593,6 +697,7 @@ impl InteractiveMigration<'_> {
                     Err(e) => return Err(e),
                 };
             };
+            statements = substitute_statements(proposal, &input)?;
         } else {
             let prompt = if let Some(prompt) = &proposal.prompt {
                 prompt
@@ -609,12 +714,44 @@ impl InteractiveMigration<'_> {
                             .ping_while(get_user_input(&proposal.required_user_input))
                             .await;
                         match input_res {
-                            Ok(data) => input = data,
+                            Ok(data) => statements = substitute_statements(proposal, &data)?,
                             Err(e) if e.is::<Refused>() => continue,
                             Err(e) => return Err(e),
                         };
                         break;
                     }
+                    Edit => {
+                        let input_res = self
+                            .cli
+                            .ping_while(get_user_input(&proposal.required_user_input))
+                            .await;
+                        let input = match input_res {
+                            Ok(data) => data,
+                            Err(e) if e.is::<Refused>() => continue,
+                            Err(e) => return Err(e),
+                        };
+                        let text = substitute_statements(proposal, &input)?.join("\n");
+                        let edited = self
+                            .cli
+                            .ping_while(async { unblock(move || spawn_editor(&text)).await? })
+                            .await;
+                        match edited {
+                            Ok(text) if !text.trim().is_empty() => {
+                                // Applied as a whole, so it's checked and
+                                // rolled back like proposed statements
+                                statements = vec![text];
+                                break;
+                            }
+                            Ok(_) => {
+                                eprintln!("No statements left after editing.");
+                                continue;
+                            }
+                            Err(e) => {
+                                print::error(format!("Error editing statements: {:#}", e));
+                                continue;
+                            }
+                        }
+                    }
                     No => {
                         execute(self.cli, "ALTER CURRENT MIGRATION REJECT PROPOSED").await?;
                         self.save_point += 1;
@@ -655,13 +792,12 @@ impl InteractiveMigration<'_> {
                 }
             }
         }
-        for statement in &proposal.statements {
-            let text = substitute_placeholders(&statement.text, &input)?;
-            match execute(self.cli, &text).await {
+        for text in &statements {
+            match execute(self.cli, text).await {
                 Ok(()) => {}
                 Err(e) => {
                     if e.is::<QueryError>() {
-                        print_query_error(&e, &text, false, "<statement>")?;
+                        print_query_error(&e, text, false, "<statement>")?;
                     } else if print::use_color() {
                         eprintln!(
                             "{}: {:#}",
@@ -711,7 +847,7 @@ impl InteractiveMigration<'_> {
     }
 }
 
-async fn run_interactive(
+pub async fn run_interactive(
     _ctx: &Context,
     cli: &mut Connection,
     key: MigrationKey,
@@ -765,6 +901,14 @@ where
     }
     fs::remove_file(&tmp_file).await.ok();
     let mut file = io::BufWriter::new(fs::File::create(&tmp_file).await?);
+    let comments = descr.comments();
+    for comment in comments {
+        file.write_all(format!("# {}\n", comment).as_bytes())
+            .await?;
+    }
+    if !comments.is_empty() {
+        file.write_all(b"\n").await?;
+    }
     file.write_all(format!("CREATE MIGRATION {}\n", id).as_bytes())
         .await?;
     file.write_all(format!("    ONTO {}\n", descr.parent()?).as_bytes())
@@ -850,10 +994,92 @@ async fn _create(
             timeout::restore_for_transaction(cli, old_timeout).await
         }
     }?;
+    // Only a plain `migration create` asks for the message: squashing and
+    // dev mode create migrations without extra questions
+    let message = match &create.message {
+        Some(message) => Some(message.clone()),
+        None if !create.non_interactive && stdin().is_terminal() => {
+            let message = cli
+                .ping_while(
+                    question::String::new("Describe the migration (leave empty to skip)")
+                        .async_ask(),
+                )
+                .await?;
+            Some(message)
+        }
+        None => None,
+    };
+    let create = &CreateMigration {
+        message,
+        ..create.clone()
+    };
+    let migration = add_metadata(&ctx, create, migration);
     write_migration(&ctx, &migration, !create.non_interactive).await?;
     Ok(())
 }
 
+/// Adds the message given by `--message` and the header comments
+/// configured in edgedb.toml to a newly created migration.
+pub fn add_metadata(
+    ctx: &Context,
+    create: &CreateMigration,
+    mut migration: FutureMigration,
+) -> FutureMigration {
+    if let Some(message) = create.message.as_ref().filter(|m| !m.trim().is_empty()) {
+        migration.set_message(message.trim());
+    }
+    if let Some(template) = &ctx.migrations.header_template {
+        migration.set_comments(render_header(template, create.ticket.as_deref()));
+    }
+    migration
+}
+
+fn git_author() -> Option<String> {
+    let config = |key| {
+        let out = std::process::Command::new("git")
+            .args(["config", key])
+            .stderr(std::process::Stdio::null())
+            .output()
+            .ok()?;
+        let value = String::from_utf8(out.stdout).ok()?.trim().to_string();
+        Some(value).filter(|v| out.status.success() && !v.is_empty())
+    };
+    match (config("user.name"), config("user.email")) {
+        (Some(name), Some(email)) => Some(format!("{} <{}>", name, email)),
+        (name, email) => name.or(email),
+    }
+}
+
+/// Substitutes `{author}`, `{timestamp}` and `{ticket}` in the header
+/// template. Lines having a placeholder with no value are skipped.
+fn render_header(template: &str, ticket: Option<&str>) -> Vec<String> {
+    let mut author = None;
+    let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    let mut lines = Vec::new();
+    'lines: for line in template.lines() {
+        let mut result = line.to_string();
+        for placeholder in ["{author}", "{timestamp}", "{ticket}"] {
+            if !result.contains(placeholder) {
+                continue;
+            }
+            let value = match placeholder {
+                "{author}" => author.get_or_insert_with(git_author).as_deref(),
+                "{timestamp}" => Some(&timestamp[..]),
+                _ => ticket,
+            };
+            match value {
+                Some(value) => result = result.replace(placeholder, value),
+                None => continue 'lines,
+            }
+        }
+        lines.push(result.trim_end().to_string());
+    }
+    while lines.last().map(|l| l.is_empty()).unwrap_or(false) {
+        lines.pop();
+    }
+    lines
+}
+
 pub async fn normal_migration(
     cli: &mut Connection,
     ctx: &Context,
@@ -947,6 +1173,17 @@ async fn get_user_input(
     Ok(result)
 }
 
+fn substitute_statements(
+    proposal: &Proposal,
+    input: &BTreeMap<String, String>,
+) -> anyhow::Result<Vec<String>> {
+    proposal
+        .statements
+        .iter()
+        .map(|s| Ok(substitute_placeholders(&s.text, input)?.into_owned()))
+        .collect()
+}
+
 fn substitute_placeholders<'x>(
     input: &'x str,
     placeholders: &BTreeMap<String, String>,
@@ -1014,6 +1251,15 @@ fn add_newline() {
     );
 }
 
+#[test]
+fn header_template() {
+    let template = "Ticket: {ticket}\nCreated: {timestamp}\n";
+    assert_eq!(render_header(template, None).len(), 1);
+    let lines = render_header(template, Some("PROJ-12"));
+    assert_eq!(lines[0], "Ticket: PROJ-12");
+    assert!(lines[1].starts_with("Created: "));
+}
+
 #[tokio::test]
 async fn start_migration() {
     use std::env;
@@ -1024,6 +1270,7 @@ async fn start_migration() {
     let ctx = Context {
         schema_dir,
         edgedb_version: None,
+        migrations: Default::default(),
         quiet: false,
     };
 
@@ -24,10 +24,20 @@ pub(crate) trait SortableMigration {
 pub(crate) struct DBMigration {
     pub(crate) name: String,
     pub(crate) script: String,
+    pub(crate) message: Option<String>,
     pub(crate) parent_names: Vec<String>,
     pub(crate) generated_by: Option<MigrationGeneratedBy>,
 }
 
+impl MigrationGeneratedBy {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            MigrationGeneratedBy::DevMode => "DevMode",
+            MigrationGeneratedBy::DDLStatement => "DDLStatement",
+        }
+    }
+}
+
 impl SortableMigration for DBMigration {
     type ParentsIter<'a> = std::slice::Iter<'a, String>;
 
@@ -91,6 +101,7 @@ pub(crate) async fn read_all(
             SELECT schema::Migration {
                 name,
                 script := .script if <bool>$0 else "",
+                message,
                 parent_names := .parents.name,
                 generated_by,
             }
@@ -111,6 +122,7 @@ pub(crate) async fn read_all(
             SELECT schema::Migration {
                 name,
                 script := .script if <bool>$0 else "",
+                message,
                 parent_names := .parents.name,
                 generated_by := <schema::Cardinality>{},
             }
@@ -135,6 +147,7 @@ pub(crate) async fn find_by_prefix(
             SELECT schema::Migration {
                 name,
                 script,
+                message,
                 parent_names := .parents.name,
                 generated_by,
             }
@@ -149,6 +162,7 @@ pub(crate) async fn find_by_prefix(
             SELECT schema::Migration {
                 name,
                 script,
+                message,
                 parent_names := .parents.name,
                 generated_by := <schema::Cardinality>{},
             }
@@ -10,9 +10,9 @@ use crate::async_try;
 use crate::bug;
 use crate::commands::Options;
 use crate::migrations::context::Context;
+use crate::migrations::create::{add_metadata, write_migration, MigrationKey};
 use crate::migrations::create::{execute_start_migration, unsafe_populate};
 use crate::migrations::create::{first_migration, normal_migration};
-use crate::migrations::create::{write_migration, MigrationKey};
 use crate::migrations::create::{CurrentMigration, FutureMigration};
 use crate::migrations::edb::{execute, execute_if_connected, query_row};
 use crate::migrations::migrate::{apply_migrations, apply_migrations_inner};
@@ -55,7 +55,13 @@ pub async fn check_client(cli: &mut Connection) -> anyhow::Result<bool> {
     ver::check_client(cli, &MINIMUM_VERSION).await
 }
 
-pub async fn migrate(cli: &mut Connection, ctx: &Context, bar: &ProgressBar) -> anyhow::Result<()> {
+/// Applies migrations and then schema changes. Returns the DDL applied to
+/// get from the migrations to the current schema.
+pub async fn migrate(
+    cli: &mut Connection,
+    ctx: &Context,
+    bar: &ProgressBar,
+) -> anyhow::Result<Vec<String>> {
     if !check_client(cli).await? {
         anyhow::bail!(
             "Dev mode is not supported on EdgeDB {}. Please upgrade.",
@@ -64,7 +70,7 @@ pub async fn migrate(cli: &mut Connection, ctx: &Context, bar: &ProgressBar) ->
     }
     let migrations = migration::read_all(ctx, true).await?;
     let db_migration = get_db_migration(cli).await?;
-    match select_mode(cli, &migrations, db_migration.as_deref()).await? {
+    let ddl = match select_mode(cli, &migrations, db_migration.as_deref()).await? {
         Mode::Normal { skip } => {
             log::info!("Skipping {} revisions.", skip);
             let migrations = migrations
@@ -76,18 +82,19 @@ pub async fn migrate(cli: &mut Connection, ctx: &Context, bar: &ProgressBar) ->
             }
             bar.set_message("calculating diff");
             log::info!("Calculating schema diff.");
-            migrate_to_schema(cli, ctx).await?;
+            migrate_to_schema(cli, ctx).await?
         }
         Mode::Rebase => {
             log::info!("Calculating schema diff.");
             bar.set_message("calculating diff");
-            migrate_to_schema(cli, ctx).await?;
+            let ddl = migrate_to_schema(cli, ctx).await?;
             log::info!("Now rebasing on top of filesystem migrations.");
             bar.set_message("rebasing migrations");
             rebase_to_schema(cli, ctx, &migrations).await?;
+            ddl
         }
-    }
-    Ok(())
+    };
+    Ok(ddl)
 }
 
 async fn select_mode(
@@ -144,7 +151,7 @@ async fn get_db_migration(cli: &mut Connection) -> anyhow::Result<Option<String>
     Ok(res)
 }
 
-async fn migrate_to_schema(cli: &mut Connection, ctx: &Context) -> anyhow::Result<()> {
+async fn migrate_to_schema(cli: &mut Connection, ctx: &Context) -> anyhow::Result<Vec<String>> {
     use edgedb_protocol::server_message::TransactionState::NotInTransaction;
 
     let transaction = matches!(cli.transaction_state(), NotInTransaction);
@@ -164,7 +171,7 @@ async fn migrate_to_schema(cli: &mut Connection, ctx: &Context) -> anyhow::Resul
     }
 }
 
-async fn _migrate_to_schema(cli: &mut Connection, ctx: &Context) -> anyhow::Result<()> {
+async fn _migrate_to_schema(cli: &mut Connection, ctx: &Context) -> anyhow::Result<Vec<String>> {
     execute(cli, "DECLARE SAVEPOINT migrate_to_schema").await?;
     let descr = async_try! {
         async {
@@ -209,7 +216,7 @@ async fn _migrate_to_schema(cli: &mut Connection, ctx: &Context) -> anyhow::Resu
     if !descr.confirmed.is_empty() {
         ddl::apply_statements(cli, &descr.confirmed).await?;
     }
-    Ok(())
+    Ok(descr.confirmed)
 }
 
 pub async fn rebase_to_schema(
@@ -287,6 +294,7 @@ pub async fn create(
             timeout::restore_for_transaction(cli, old_timeout).await
         }
     }?;
+    let migration = add_metadata(ctx, create, migration);
     write_migration(ctx, &migration, !create.non_interactive).await?;
     Ok(())
 }
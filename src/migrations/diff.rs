@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+
+use colorful::Colorful;
+
+use crate::async_try;
+use crate::commands::Options;
+use crate::connect::Connection;
+use crate::migrations::context::Context;
+use crate::migrations::create::{
+    apply_proposal, execute_start_migration, make_default_expression, print_statements,
+    CurrentMigration,
+};
+use crate::migrations::edb::{execute, execute_if_connected, query_row};
+use crate::migrations::options::MigrationDiff;
+use crate::migrations::timeout;
+use crate::print;
+
+pub struct ProposedChange {
+    pub prompt: Option<String>,
+    pub confidence: f64,
+    pub statements: Vec<String>,
+}
+
+pub struct SchemaDiff {
+    pub confirmed: Vec<String>,
+    pub proposed: Vec<ProposedChange>,
+    /// Set if the server could not produce a complete migration without
+    /// answers from the user
+    pub incomplete: bool,
+}
+
+pub async fn diff(
+    cli: &mut Connection,
+    _options: &Options,
+    params: &MigrationDiff,
+) -> anyhow::Result<()> {
+    let old_state = cli.set_ignore_error_state();
+    let res = _diff(cli, params).await;
+    cli.restore_state(old_state);
+    res
+}
+
+async fn _diff(cli: &mut Connection, params: &MigrationDiff) -> anyhow::Result<()> {
+    let ctx = Context::from_project_or_config(&params.cfg, false).await?;
+    let old_timeout = timeout::inhibit_for_transaction(cli).await?;
+    let diff = async_try! {
+        async {
+            execute_start_migration(&ctx, cli).await?;
+            async_try! {
+                async {
+                    collect_diff(cli).await
+                },
+                finally async {
+                    execute_if_connected(cli, "ABORT MIGRATION").await
+                }
+            }
+        },
+        finally async {
+            timeout::restore_for_transaction(cli, old_timeout).await
+        }
+    }?;
+    print_diff(&diff);
+    Ok(())
+}
+
+/// Walks through all proposals of the current migration, accepting them
+/// the same way `--allow-unsafe` does. The migration is aborted afterwards
+/// so nothing is committed.
+pub async fn collect_diff(cli: &mut Connection) -> anyhow::Result<SchemaDiff> {
+    let mut data = query_row::<CurrentMigration>(cli, "DESCRIBE CURRENT MIGRATION AS JSON").await?;
+    let mut diff = SchemaDiff {
+        confirmed: data.confirmed.clone(),
+        proposed: Vec::new(),
+        incomplete: false,
+    };
+    while !data.complete {
+        let Some(proposal) = &data.proposed else {
+            diff.incomplete = true;
+            break;
+        };
+        let mut placeholders = BTreeMap::new();
+        for input in &proposal.required_user_input {
+            if let Some(expr) = make_default_expression(input) {
+                placeholders.insert(input.placeholder.clone(), expr);
+            }
+        }
+        let change = ProposedChange {
+            prompt: proposal.prompt.clone(),
+            confidence: proposal.confidence,
+            statements: proposal.statements.iter().map(|s| s.text.clone()).collect(),
+        };
+        if placeholders.len() < proposal.required_user_input.len() {
+            diff.proposed.push(change);
+            diff.incomplete = true;
+            break;
+        }
+        if apply_proposal(cli, proposal, &placeholders).await? {
+            diff.proposed.push(change);
+        } else {
+            execute(cli, "ALTER CURRENT MIGRATION REJECT PROPOSED").await?;
+        }
+        data = query_row::<CurrentMigration>(cli, "DESCRIBE CURRENT MIGRATION AS JSON").await?;
+    }
+    Ok(diff)
+}
+
+pub fn print_diff(diff: &SchemaDiff) {
+    if diff.confirmed.is_empty() && diff.proposed.is_empty() {
+        print::success("No schema changes detected.");
+        return;
+    }
+    if !diff.confirmed.is_empty() {
+        println!("Confirmed statements:");
+        print_statements(&diff.confirmed);
+    }
+    for change in &diff.proposed {
+        let prompt = change.prompt.as_deref().unwrap_or("Proposed statements");
+        if print::use_color() {
+            println!(
+                "{} {}",
+                prompt.bold().white(),
+                format!("(confidence: {:.2})", change.confidence).dark_gray(),
+            );
+        } else {
+            println!("{} (confidence: {:.2})", prompt, change.confidence);
+        }
+        print_statements(&change.statements);
+    }
+    if diff.incomplete {
+        print::warn(
+            "The list of changes is incomplete: some of the changes \
+            require user input. Run `edgedb migration create` to \
+            resolve them.",
+        );
+    }
+}
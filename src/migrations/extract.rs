@@ -54,6 +54,7 @@ pub async fn extract(
     let temp_ctx = Context {
         schema_dir: temp_dir.path().to_path_buf(),
         edgedb_version: None,
+        migrations: Default::default(),
         quiet: false,
     };
     let mut to_delete = Vec::new();
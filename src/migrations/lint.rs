@@ -0,0 +1,517 @@
+use std::collections::BTreeMap;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use colorful::Colorful;
+use edgeql_parser::tokenizer::{Kind, Token, Tokenizer};
+use fn_error_context::context;
+use tokio::fs;
+
+use crate::commands::{ExitCode, Options};
+use crate::migrations::context::Context;
+use crate::migrations::grammar::parse_migration;
+use crate::migrations::migration;
+use crate::migrations::options::MigrationLint;
+use crate::portable::config::LintLevel;
+use crate::print;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    DropType,
+    DropProperty,
+    RequiredWithoutDefault,
+    CardinalityChange,
+    DropCreateRename,
+    NonConcurrentIndex,
+}
+
+const RULES: &[Rule] = &[
+    Rule::DropType,
+    Rule::DropProperty,
+    Rule::RequiredWithoutDefault,
+    Rule::CardinalityChange,
+    Rule::DropCreateRename,
+    Rule::NonConcurrentIndex,
+];
+
+#[derive(Debug, serde::Serialize)]
+struct Finding {
+    file: PathBuf,
+    line: usize,
+    column: usize,
+    rule: &'static str,
+    level: &'static str,
+    message: String,
+}
+
+#[derive(Default)]
+struct Statement<'a> {
+    head: Vec<Token<'a>>,
+    body: Vec<Statement<'a>>,
+}
+
+struct Checker<'a> {
+    path: &'a Path,
+    text: &'a str,
+    levels: &'a BTreeMap<String, LintLevel>,
+    findings: Vec<Finding>,
+}
+
+impl Rule {
+    fn name(self) -> &'static str {
+        match self {
+            Rule::DropType => "drop-type",
+            Rule::DropProperty => "drop-property",
+            Rule::RequiredWithoutDefault => "required-without-default",
+            Rule::CardinalityChange => "cardinality-change",
+            Rule::DropCreateRename => "drop-create-rename",
+            Rule::NonConcurrentIndex => "non-concurrent-index",
+        }
+    }
+    fn default_level(self) -> LintLevel {
+        match self {
+            Rule::DropType | Rule::DropProperty | Rule::RequiredWithoutDefault => LintLevel::Error,
+            Rule::CardinalityChange | Rule::DropCreateRename | Rule::NonConcurrentIndex => {
+                LintLevel::Warn
+            }
+        }
+    }
+}
+
+pub async fn lint(_options: &Options, params: &MigrationLint) -> anyhow::Result<()> {
+    let ctx = Context::from_project_or_config(&params.cfg, false).await?;
+    for name in ctx.migrations.lint.keys() {
+        if !RULES.iter().any(|r| r.name() == name) {
+            log::warn!(
+                "Unknown lint rule `{}` in edgedb.toml",
+                name.escape_default()
+            );
+        }
+    }
+    let files = if params.files.is_empty() {
+        migration::read_all(&ctx, false)
+            .await?
+            .into_values()
+            .map(|m| m.path)
+            .collect()
+    } else {
+        params.files.clone()
+    };
+    let mut findings = Vec::new();
+    for path in &files {
+        findings.extend(lint_file(path, &ctx.migrations.lint).await?);
+    }
+
+    if params.json {
+        println!("{}", serde_json::to_string_pretty(&findings)?);
+    } else {
+        print_findings(&findings, files.len());
+    }
+    if findings.iter().any(|f| f.level == "error") {
+        return Err(ExitCode::new(1))?;
+    }
+    Ok(())
+}
+
+#[tokio::main(flavor = "current_thread")]
+pub async fn lint_fs(options: &Options, params: &MigrationLint) -> anyhow::Result<()> {
+    lint(options, params).await
+}
+
+#[context("could not lint migration file {}", path.display())]
+async fn lint_file(
+    path: &Path,
+    levels: &BTreeMap<String, LintLevel>,
+) -> anyhow::Result<Vec<Finding>> {
+    let text = fs::read_to_string(path).await?;
+    check(path, &text, levels)
+}
+
+fn check(
+    path: &Path,
+    text: &str,
+    levels: &BTreeMap<String, LintLevel>,
+) -> anyhow::Result<Vec<Finding>> {
+    // Validates the overall structure of the file
+    parse_migration(text)?;
+
+    let mut tokens = Tokenizer::new(text)
+        .map_while(Result::ok)
+        .skip_while(|t| t.kind != Kind::OpenBrace)
+        .skip(1);
+    let statements = parse_block(&mut tokens);
+    let mut checker = Checker {
+        path,
+        text,
+        levels,
+        findings: Vec::new(),
+    };
+    checker.check_block(&[], &statements);
+    Ok(checker.findings)
+}
+
+fn print_findings(findings: &[Finding], files: usize) {
+    for f in findings {
+        let location = format!("{}:{}:{}", f.file.display(), f.line, f.column);
+        if print::use_color() {
+            let level = if f.level == "error" {
+                f.level.bold().light_red()
+            } else {
+                f.level.bold().yellow()
+            };
+            println!(
+                "{}: {}{}: {}",
+                location.bold().white(),
+                level,
+                format!("[{}]", f.rule).dark_gray(),
+                f.message
+            );
+        } else {
+            println!("{}: {}[{}]: {}", location, f.level, f.rule, f.message);
+        }
+    }
+    if findings.is_empty() {
+        print::success(format!("No issues found in {} migration files.", files));
+    } else {
+        let errors = findings.iter().filter(|f| f.level == "error").count();
+        eprintln!(
+            "Found {} errors and {} warnings in {} migration files.",
+            errors,
+            findings.len() - errors,
+            files,
+        );
+    }
+}
+
+/// Splits tokens into statements up to the closing brace. Only the first
+/// brace block of a statement is treated as its body: later ones are shapes
+/// or other expressions.
+fn parse_block<'a>(tokens: &mut impl Iterator<Item = Token<'a>>) -> Vec<Statement<'a>> {
+    let mut result = Vec::new();
+    let mut current = Statement::default();
+    while let Some(token) = tokens.next() {
+        match token.kind {
+            Kind::OpenBrace => {
+                let body = parse_block(tokens);
+                if current.body.is_empty() {
+                    current.body = body;
+                }
+            }
+            Kind::CloseBrace => break,
+            Kind::Semicolon => {
+                if !current.head.is_empty() {
+                    result.push(mem::take(&mut current));
+                }
+            }
+            _ if current.body.is_empty() => current.head.push(token),
+            _ => {}
+        }
+    }
+    if !current.head.is_empty() {
+        result.push(current);
+    }
+    result
+}
+
+fn lower_words(head: &[Token]) -> Vec<String> {
+    head.iter().map(|t| t.text.to_lowercase()).collect()
+}
+
+fn has_word(words: &[String], word: &str) -> bool {
+    words.iter().any(|w| w == word)
+}
+
+fn starts_with(words: &[String], prefix: &[&str]) -> bool {
+    words.len() >= prefix.len() && words.iter().zip(prefix).all(|(w, p)| w == p)
+}
+
+/// Returns the index of the object kind keyword in a `CREATE`, `ALTER` or
+/// `DROP` statement
+fn object_kind(words: &[String]) -> Option<usize> {
+    if !matches!(
+        words.first().map(|w| &w[..]),
+        Some("create" | "alter" | "drop")
+    ) {
+        return None;
+    }
+    words
+        .iter()
+        .position(|w| matches!(&w[..], "type" | "property" | "link" | "index"))
+}
+
+fn name_at(head: &[Token], mut idx: usize) -> String {
+    let mut name = String::new();
+    while let Some(token) = head.get(idx) {
+        if !matches!(
+            token.kind,
+            Kind::Ident | Kind::Keyword(_) | Kind::BacktickName
+        ) {
+            break;
+        }
+        name.push_str(&token.text);
+        if head.get(idx + 1).map(|t| t.kind == Kind::Namespace) == Some(true) {
+            name.push_str("::");
+            idx += 2;
+        } else {
+            break;
+        }
+    }
+    name
+}
+
+/// Returns the name of the innermost enclosing type and whether it is
+/// an existing type being altered
+fn enclosing_type(parents: &[&Statement]) -> Option<(String, bool)> {
+    parents.iter().rev().find_map(|stmt| {
+        let words = lower_words(&stmt.head);
+        let idx = object_kind(&words)?;
+        if words[idx] != "type" {
+            return None;
+        }
+        Some((name_at(&stmt.head, idx + 1), words[0] == "alter"))
+    })
+}
+
+fn line_col(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map(|p| p + 1).unwrap_or(0);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+impl Checker<'_> {
+    fn report(&mut self, rule: Rule, token: &Token, message: String) {
+        let level = self
+            .levels
+            .get(rule.name())
+            .copied()
+            .unwrap_or(rule.default_level());
+        let level = match level {
+            LintLevel::Allow => return,
+            LintLevel::Warn => "warning",
+            LintLevel::Error => "error",
+        };
+        let (line, column) = line_col(self.text, token.span.start as usize);
+        self.findings.push(Finding {
+            file: self.path.to_path_buf(),
+            line,
+            column,
+            rule: rule.name(),
+            level,
+            message,
+        });
+    }
+
+    fn check_block(&mut self, parents: &[&Statement], block: &[Statement]) {
+        let mut dropped = Vec::new();
+        for stmt in block {
+            let words = lower_words(&stmt.head);
+            if let Some(idx) = object_kind(&words) {
+                let kind = &words[idx];
+                let name = name_at(&stmt.head, idx + 1);
+                if words[0] == "drop" && kind != "index" {
+                    dropped.push((kind.clone(), name.clone()));
+                } else if words[0] == "create" {
+                    if let Some((_, old)) = dropped.iter().find(|(k, _)| k == kind) {
+                        self.report(
+                            Rule::DropCreateRename,
+                            &stmt.head[0],
+                            format!(
+                                "{kind} `{name}` is created while `{old}` is dropped; \
+                                if this is a rename, use `RENAME TO` to keep the data"
+                            ),
+                        );
+                    }
+                }
+            }
+            self.check_statement(parents, stmt, &words);
+
+            let mut chain = parents.to_vec();
+            chain.push(stmt);
+            self.check_block(&chain, &stmt.body);
+        }
+    }
+
+    fn check_statement(&mut self, parents: &[&Statement], stmt: &Statement, words: &[String]) {
+        let start = &stmt.head[0];
+        let of_type = enclosing_type(parents)
+            .map(|(name, _)| format!(" of `{name}`"))
+            .unwrap_or_default();
+
+        if let Some(idx) = object_kind(words) {
+            let kind = &words[idx][..];
+            let name = name_at(&stmt.head, idx + 1);
+            match (&words[0][..], kind) {
+                ("drop", "type") => self.report(
+                    Rule::DropType,
+                    start,
+                    format!("dropping type `{name}` deletes all of its objects"),
+                ),
+                ("drop", "property" | "link") => self.report(
+                    Rule::DropProperty,
+                    start,
+                    format!("dropping {kind} `{name}`{of_type} deletes its data"),
+                ),
+                ("create", "property" | "link")
+                    if has_word(&words[..idx], "required")
+                        && !has_word(words, "using")
+                        && !stmt
+                            .body
+                            .iter()
+                            .any(|s| starts_with(&lower_words(&s.head), &["set", "default"])) =>
+                {
+                    if let Some((type_name, true)) = enclosing_type(parents) {
+                        self.report(
+                            Rule::RequiredWithoutDefault,
+                            start,
+                            format!(
+                                "adding required {kind} `{name}` to existing type \
+                                `{type_name}` without a default fails if the type \
+                                has any objects"
+                            ),
+                        );
+                    }
+                }
+                ("create", "index") if !has_word(words, "deferred") => {
+                    if let Some((type_name, true)) = enclosing_type(parents) {
+                        self.report(
+                            Rule::NonConcurrentIndex,
+                            start,
+                            format!(
+                                "index on existing type `{type_name}` is built \
+                                synchronously and blocks writes until it is done; \
+                                consider `CREATE DEFERRED INDEX` or a low-traffic window"
+                            ),
+                        );
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        // Subcommands of `ALTER PROPERTY` and `ALTER LINK`
+        let Some(parent) = parents.last() else { return };
+        let parent_words = lower_words(&parent.head);
+        let Some(idx) = object_kind(&parent_words) else {
+            return;
+        };
+        let kind = &parent_words[idx];
+        if kind != "property" && kind != "link" {
+            return;
+        }
+        let name = name_at(&parent.head, idx + 1);
+        if starts_with(words, &["set", "required"]) && !has_word(words, "using") {
+            self.report(
+                Rule::RequiredWithoutDefault,
+                start,
+                format!(
+                    "making {kind} `{name}`{of_type} required without `USING` \
+                    fails if any object has no value"
+                ),
+            );
+        } else if starts_with(words, &["set", "single"]) {
+            self.report(
+                Rule::CardinalityChange,
+                start,
+                format!(
+                    "changing {kind} `{name}`{of_type} to single fails or needs \
+                    a conversion if any object has multiple values"
+                ),
+            );
+        } else if starts_with(words, &["set", "multi"]) {
+            self.report(
+                Rule::CardinalityChange,
+                start,
+                format!("changing {kind} `{name}`{of_type} to multi rewrites its data"),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    use super::check;
+    use crate::portable::config::LintLevel;
+
+    fn rules(body: &str) -> Vec<(&'static str, usize)> {
+        let text = format!("CREATE MIGRATION m1xxx ONTO initial {{\n{body}\n}};\n");
+        check(Path::new("test.edgeql"), &text, &BTreeMap::new())
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.rule, f.line))
+            .collect()
+    }
+
+    #[test]
+    fn drops() {
+        assert_eq!(rules("DROP TYPE default::User;"), [("drop-type", 2)]);
+        assert_eq!(
+            rules("ALTER TYPE default::User {\n  DROP PROPERTY name;\n};"),
+            [("drop-property", 3)]
+        );
+    }
+
+    #[test]
+    fn rename() {
+        assert_eq!(
+            rules(
+                "ALTER TYPE default::User {\n  \
+                   DROP PROPERTY name;\n  \
+                   CREATE PROPERTY full_name: std::str;\n\
+                 };"
+            ),
+            [("drop-property", 3), ("drop-create-rename", 4)]
+        );
+    }
+
+    #[test]
+    fn required() {
+        assert_eq!(
+            rules("ALTER TYPE User {\n  CREATE REQUIRED PROPERTY name: str;\n};"),
+            [("required-without-default", 3)]
+        );
+        assert!(rules(
+            "ALTER TYPE User {\n  \
+                   CREATE REQUIRED PROPERTY name: str {\n    \
+                     SET default := '';\n  \
+                   };\n\
+                 };"
+        )
+        .is_empty());
+        assert!(rules("CREATE TYPE User {\n  CREATE REQUIRED PROPERTY name: str;\n};").is_empty());
+        assert_eq!(
+            rules(
+                "ALTER TYPE User {\n  \
+                   ALTER PROPERTY name {\n    \
+                     SET REQUIRED;\n    \
+                     SET MULTI;\n  \
+                   };\n\
+                 };"
+            ),
+            [("required-without-default", 4), ("cardinality-change", 5)]
+        );
+    }
+
+    #[test]
+    fn configured_levels() {
+        let text = "CREATE MIGRATION m1xxx ONTO initial {\n\
+            ALTER TYPE User { CREATE INDEX ON (.name); };\n\
+        };\n";
+        let mut levels = BTreeMap::new();
+        let found = check(Path::new("test.edgeql"), text, &levels).unwrap();
+        assert_eq!(found[0].level, "warning");
+        levels.insert("non-concurrent-index".into(), LintLevel::Error);
+        let found = check(Path::new("test.edgeql"), text, &levels).unwrap();
+        assert_eq!(found[0].level, "error");
+        levels.insert("non-concurrent-index".into(), LintLevel::Allow);
+        assert!(check(Path::new("test.edgeql"), text, &levels)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -1,20 +1,37 @@
+use std::path::PathBuf;
+
+use indexmap::IndexMap;
+
 use crate::commands::Options;
 use crate::connect::Connection;
 use crate::migrations::context::Context;
+use crate::migrations::db_migration::{DBMigration, MigrationGeneratedBy};
+use crate::migrations::migration::MigrationFile;
 use crate::migrations::options::MigrationLog;
-use crate::migrations::{db_migration, migration};
+use crate::migrations::{db_migration, migration, NULL_MIGRATION};
+
+#[derive(serde::Serialize)]
+struct LogEntry {
+    id: String,
+    parent: Option<String>,
+    message: Option<String>,
+    generated_by: Option<&'static str>,
+    path: Option<PathBuf>,
+}
 
 pub async fn log(
     cli: &mut Connection,
     common: &Options,
     options: &MigrationLog,
 ) -> Result<(), anyhow::Error> {
-    if options.from_fs {
+    if options.graph {
+        return log_graph(cli, common, options).await;
+    } else if options.from_fs {
         log_fs_async(common, options).await
     } else if options.from_db {
         return log_db(cli, common, options).await;
     } else {
-        anyhow::bail!("use either --from-fs or --from-db");
+        anyhow::bail!("use either --from-fs, --from-db or --graph");
     }
 }
 
@@ -35,19 +52,120 @@ async fn _log_db(
     options: &MigrationLog,
 ) -> Result<(), anyhow::Error> {
     let migrations = db_migration::read_all(cli, false, false).await?;
+    if options.json {
+        // Filesystem is optional for `--from-db`, so paths are only
+        // filled in when migration files can be found
+        let files = match Context::from_project_or_config(&options.cfg, true).await {
+            Ok(ctx) => migration::read_all(&ctx, false).await.unwrap_or_default(),
+            Err(_) => Default::default(),
+        };
+        let entries = migrations
+            .into_values()
+            .map(|m| LogEntry {
+                path: files.get(&m.name).map(|f| f.path.clone()),
+                parent: m.parent_names.first().cloned(),
+                message: m.message,
+                generated_by: m.generated_by.as_ref().map(|g| g.as_str()),
+                id: m.name,
+            })
+            .collect();
+        return print_json(entries, options);
+    }
     let limit = options.limit.unwrap_or(migrations.len());
     if options.newest_first {
-        for rev in migrations.iter().rev().take(limit) {
-            println!("{}", rev.0);
+        for (id, rev) in migrations.iter().rev().take(limit) {
+            print_entry(id, rev.message.as_deref());
         }
     } else {
-        for rev in migrations.iter().take(limit) {
-            println!("{}", rev.0);
+        for (id, rev) in migrations.iter().take(limit) {
+            print_entry(id, rev.message.as_deref());
         }
     }
     Ok(())
 }
 
+async fn log_graph(
+    cli: &mut Connection,
+    _common: &Options,
+    options: &MigrationLog,
+) -> Result<(), anyhow::Error> {
+    let old_state = cli.set_ignore_error_state();
+    let res = _log_graph(cli, options).await;
+    cli.restore_state(old_state);
+    res
+}
+
+async fn _log_graph(cli: &mut Connection, options: &MigrationLog) -> Result<(), anyhow::Error> {
+    let ctx = Context::from_project_or_config(&options.cfg, false).await?;
+    let files = migration::read_all(&ctx, false).await?;
+    let fixups = migration::read_fixups(&ctx, false).await?;
+    let db = db_migration::read_all(cli, false, false).await?;
+    for line in graph(&files, &fixups, &db) {
+        println!("{}", line.trim_end());
+    }
+    Ok(())
+}
+
+/// Draws file and database histories newest first. Revisions present in
+/// both are drawn once, a diverged tail of each history gets its own
+/// column, with file revisions on the left.
+fn graph(
+    files: &IndexMap<String, MigrationFile>,
+    fixups: &[MigrationFile],
+    db: &IndexMap<String, DBMigration>,
+) -> Vec<String> {
+    let common = files
+        .keys()
+        .zip(db.keys())
+        .take_while(|(file, db)| file == db)
+        .count();
+    let head = db.keys().last();
+    let notes = |id: &String| {
+        let mut notes = Vec::new();
+        if let Some(file) = files.get(id) {
+            if let Some(name) = file.path.file_name() {
+                notes.push(name.to_string_lossy().into_owned());
+            }
+        } else {
+            notes.push("not in files".into());
+        }
+        match db.get(id) {
+            Some(m) => match m.generated_by {
+                Some(MigrationGeneratedBy::DevMode) => notes.push("dev mode".into()),
+                Some(MigrationGeneratedBy::DDLStatement) => notes.push("DDL".into()),
+                None => {}
+            },
+            None => notes.push("not applied".into()),
+        }
+        if Some(id) == head {
+            notes.push("database head".into());
+        }
+        if fixups.iter().any(|f| f.fixup_target.as_ref() == Some(id)) {
+            notes.push("squash point".into());
+        }
+        for fixup in fixups.iter().filter(|f| &f.data.parent_id == id) {
+            if let Some(target) = &fixup.fixup_target {
+                notes.push(format!("fixup to {}", &target[..target.len().min(12)]));
+            }
+        }
+        notes
+    };
+    let row = |prefix: &str, id: &String| format!("{} {}  ({})", prefix, id, notes(id).join(", "));
+
+    let file_tail = files.keys().skip(common).rev();
+    let db_tail = db.keys().skip(common).rev();
+    let mut lines = Vec::new();
+    if files.len() > common && db.len() > common {
+        lines.extend(file_tail.map(|id| row("*", id)));
+        lines.extend(db_tail.map(|id| row("| *", id)));
+        lines.push("|/   histories diverge".into());
+    } else {
+        lines.extend(file_tail.chain(db_tail).map(|id| row("*", id)));
+    }
+    lines.extend(files.keys().take(common).rev().map(|id| row("*", id)));
+    lines
+}
+
 #[tokio::main(flavor = "current_thread")]
 pub async fn log_fs(common: &Options, options: &MigrationLog) -> Result<(), anyhow::Error> {
     log_fs_async(common, options).await
@@ -58,15 +176,46 @@ async fn log_fs_async(_common: &Options, options: &MigrationLog) -> Result<(), a
 
     let ctx = Context::from_project_or_config(&options.cfg, false).await?;
     let migrations = migration::read_all(&ctx, true).await?;
+    if options.json {
+        let entries = migrations
+            .into_iter()
+            .map(|(id, file)| LogEntry {
+                id,
+                parent: Some(file.data.parent_id).filter(|p| p != NULL_MIGRATION),
+                message: file.data.message,
+                generated_by: None,
+                path: Some(file.path),
+            })
+            .collect();
+        return print_json(entries, options);
+    }
     let limit = options.limit.unwrap_or(migrations.len());
     if options.newest_first {
-        for rev in migrations.keys().rev().take(limit) {
-            println!("{}", rev);
+        for (id, file) in migrations.iter().rev().take(limit) {
+            print_entry(id, file.data.message.as_deref());
         }
     } else {
-        for rev in migrations.keys().take(limit) {
-            println!("{}", rev);
+        for (id, file) in migrations.iter().take(limit) {
+            print_entry(id, file.data.message.as_deref());
         }
     }
     Ok(())
 }
+
+fn print_entry(id: &str, message: Option<&str>) {
+    match message.and_then(|m| m.lines().map(|l| l.trim()).find(|l| !l.is_empty())) {
+        Some(message) => println!("{}  {}", id, message),
+        None => println!("{}", id),
+    }
+}
+
+fn print_json(mut entries: Vec<LogEntry>, options: &MigrationLog) -> Result<(), anyhow::Error> {
+    if options.newest_first {
+        entries.reverse();
+    }
+    if let Some(limit) = options.limit {
+        entries.truncate(limit);
+    }
+    println!("{}", serde_json::to_string_pretty(&entries)?);
+    Ok(())
+}
@@ -149,6 +149,7 @@ pub async fn write_merge_migrations(
     let temp_ctx = Context {
         schema_dir: temp_dir.path().to_path_buf(),
         edgedb_version: None,
+        migrations: Default::default(),
         quiet: false,
     };
 
@@ -15,10 +15,11 @@ use crate::connect::Connection;
 use crate::error_display::print_query_error;
 use crate::hint::HintExt;
 use crate::migrations::context::Context;
+use crate::migrations::create::print_statements;
 use crate::migrations::db_migration;
 use crate::migrations::db_migration::{DBMigration, MigrationGeneratedBy};
 use crate::migrations::dev_mode;
-use crate::migrations::edb::{execute, execute_if_connected};
+use crate::migrations::edb::{execute, execute_if_connected, query_row};
 use crate::migrations::migration::{self, MigrationFile};
 use crate::migrations::options::Migrate;
 use crate::migrations::timeout;
@@ -103,7 +104,8 @@ async fn _migrate(
     let ctx = Context::from_project_or_config(&migrate.cfg, migrate.quiet).await?;
     if migrate.dev_mode {
         // TODO(tailhook) figure out progressbar in non-quiet mode
-        return dev_mode::migrate(cli, &ctx, &ProgressBar::hidden()).await;
+        dev_mode::migrate(cli, &ctx, &ProgressBar::hidden()).await?;
+        return Ok(());
     }
     let migrations = migration::read_all(&ctx, true).await?;
     let db_migrations = db_migration::read_all(cli, false, true).await?;
@@ -198,6 +200,9 @@ async fn _migrate(
         }
         return Ok(());
     }
+    if migrate.dry_run {
+        return dry_run(cli, migrations, migrate).await;
+    }
     apply_migrations(cli, migrations, &ctx, migrate.single_transaction).await?;
     if db_migrations.is_empty() {
         disable_ddl(cli).await?;
@@ -359,10 +364,85 @@ async fn fixup(
         }
     }
 
+    if _options.dry_run {
+        return dry_run(cli, &operations, _options).await;
+    }
     apply_migrations(cli, &operations, ctx, _options.single_transaction).await?;
     Ok(())
 }
 
+/// Prints the operations in the order they would be executed. With
+/// `--rollback` they are also applied in a transaction that is rolled back.
+async fn dry_run(
+    cli: &mut Connection,
+    operations: &(impl AsOperations + ?Sized),
+    migrate: &Migrate,
+) -> anyhow::Result<()> {
+    let mut num = 0;
+    for operation in operations.as_operations() {
+        match operation {
+            Operation::Apply(migration) => {
+                let file_name = migration.path.file_name().unwrap();
+                if print::use_color() {
+                    println!(
+                        "{} {} ({})",
+                        "Would apply".bold().light_green(),
+                        migration.data.id[..].bold().white(),
+                        Path::new(file_name).display(),
+                    );
+                } else {
+                    println!(
+                        "Would apply {} ({})",
+                        migration.data.id,
+                        Path::new(file_name).display(),
+                    );
+                }
+                let data = fs::read_to_string(&migration.path)
+                    .await
+                    .context("error re-reading migration file")?;
+                print_statements([data.trim_end()]);
+                num += 1;
+            }
+            Operation::Rewrite(migrations) => {
+                println!(
+                    "Would rewrite migration history to {} revisions, ending with {}",
+                    migrations.len(),
+                    migrations
+                        .last()
+                        .map(|(id, _)| &id[..])
+                        .unwrap_or("initial"),
+                );
+            }
+        }
+    }
+    if !migrate.rollback {
+        eprintln!("Dry run: {} migrations would be applied.", num);
+        return Ok(());
+    }
+    let old_timeout = timeout::inhibit_for_transaction(cli).await?;
+    async_try! {
+        async {
+            execute(cli, "START TRANSACTION").await?;
+            async_try! {
+                async {
+                    apply_migrations_inner(cli, operations, true).await
+                },
+                finally async {
+                    execute_if_connected(cli, "ROLLBACK").await
+                }
+            }
+        },
+        finally async {
+            timeout::restore_for_transaction(cli, old_timeout).await
+        }
+    }?;
+    print::success(format!(
+        "Dry run: {} migrations applied successfully and rolled back.",
+        num
+    ));
+    Ok(())
+}
+
 fn find_path<'a>(
     migrations: &'a IndexMap<String, MigrationFile>,
     fixups: &'a [MigrationFile],
@@ -508,6 +588,27 @@ pub async fn apply_migration(
     Ok(())
 }
 
+/// Returns the schema (as SDL) produced by applying `history` from scratch.
+///
+/// Must be called with transaction timeouts inhibited. Nothing is committed.
+pub async fn schema_at(
+    cli: &mut Connection,
+    history: &indexmap::map::Slice<String, MigrationFile>,
+) -> anyhow::Result<String> {
+    execute(cli, "START MIGRATION REWRITE").await?;
+    async_try! {
+        async {
+            for migration in history.values() {
+                apply_migration(cli, migration).await?;
+            }
+            anyhow::Ok(query_row::<String>(cli, "DESCRIBE SCHEMA AS SDL").await?)
+        },
+        finally async {
+            execute_if_connected(cli, "ABORT MIGRATION REWRITE").await
+        }
+    }
+}
+
 pub async fn apply_migrations_inner(
     cli: &mut Connection,
     migrations: &(impl AsOperations + ?Sized),
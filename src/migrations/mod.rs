@@ -1,18 +1,23 @@
+mod answers;
 mod context;
 mod create;
 mod db_migration;
+mod diff;
 mod edb;
 mod edit;
 mod extract;
 mod grammar;
+mod lint;
 mod log;
 mod migrate;
 mod migration;
 mod print_error;
 mod prompt;
-mod source_map;
+mod revert;
+pub mod source_map;
 mod squash;
 mod status;
+mod testing;
 mod timeout;
 
 pub mod dev_mode;
@@ -26,10 +31,15 @@ const NULL_MIGRATION: &str = "initial";
 
 pub use self::log::{log, log_fs};
 pub use context::Context;
-pub use create::create;
+pub use create::{create, EsdlError};
+pub use diff::diff;
 pub use edit::{edit, edit_no_check};
 pub use extract::extract;
+pub use lint::{lint, lint_fs};
 pub use migrate::migrate;
-pub use status::status;
+pub use print_error::line_column;
+pub use revert::revert;
+pub use status::{status, status_json_main};
+pub use testing::test;
 pub use upgrade_check::upgrade_check;
 pub use upgrade_format::upgrade_format;
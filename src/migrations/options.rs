@@ -27,8 +27,24 @@ pub enum MigrationCmd {
     Create(CreateMigration),
     /// Show current migration status.
     Status(ShowStatus),
+    /// Show DDL statements the next migration would contain,
+    /// without creating a migration file.
+    Diff(MigrationDiff),
     /// Show all migration versions.
     Log(MigrationLog),
+    /// Check migration files for destructive or risky DDL, such as
+    /// dropping properties. Does not need a database connection.
+    Lint(MigrationLint),
+    /// Roll the database back to an earlier revision, undoing the schema
+    /// changes of all migrations applied after it.
+    Revert(MigrationRevert),
+    /// Run migrations against a temporary branch with fixture data and
+    /// check the result using test queries from <schema-dir>/tests.
+    ///
+    /// Each `.edgeql` file is a test that passes if it runs without
+    /// errors, e.g. `select assert(count(User) > 0);`. The temporary
+    /// branch is dropped afterwards.
+    Test(MigrationTest),
     /// Edit migration file.
     ///
     /// Invokes $EDITOR on the last migration file, and then fixes
@@ -66,6 +82,15 @@ pub struct CreateMigration {
     /// Note: this discards data migrations.
     #[arg(long)]
     pub squash: bool,
+    /// With `--squash`, collapse only the revisions starting from this one
+    /// (inclusive). Defaults to the first revision. Later revisions are
+    /// kept and fixups are created for databases inside the range.
+    #[arg(long, requires = "squash", value_name = "REV")]
+    pub from: Option<String>,
+    /// With `--squash`, the last revision to collapse (inclusive). Defaults
+    /// to the last revision.
+    #[arg(long, requires = "squash", value_name = "REV")]
+    pub to: Option<String>,
     /// Do not ask questions. By default works only if "safe" changes are
     /// to be done (those for which EdgeDB has a high degree of confidence).
     /// This safe default can be overridden with `--allow-unsafe`.
@@ -79,6 +104,22 @@ pub struct CreateMigration {
     /// data-only migrations).
     #[arg(long)]
     pub allow_empty: bool,
+    /// File with answers to migration prompts, used in non-interactive
+    /// mode. TOML (or JSON, if the file has `.json` extension) with a list
+    /// of `[[answer]]` tables. Each answer has a `statement` regex matched
+    /// against the proposed statements and either `apply = true/false` or
+    /// a `placeholder` name with the `expression` to fill in.
+    #[arg(long, value_hint=ValueHint::FilePath, requires = "non_interactive")]
+    pub answers: Option<PathBuf>,
+    /// Message describing the migration. Written into the migration file
+    /// as `SET message := ...`. If omitted, an interactive `migration create`
+    /// asks for it, while squashing and dev mode leave it empty.
+    #[arg(long, short = 'm')]
+    pub message: Option<String>,
+    /// Ticket id substituted for `{ticket}` in the `header-template`
+    /// from the `[migrations]` section of edgedb.toml.
+    #[arg(long)]
+    pub ticket: Option<String>,
     /// Print queries executed.
     #[arg(long, hide = true)]
     pub debug_print_queries: bool,
@@ -127,6 +168,16 @@ pub struct Migrate {
     /// Runs the migration(s) in a single transaction.
     #[arg(long = "single-transaction")]
     pub single_transaction: bool,
+
+    /// Print the migrations that would be applied, in order, along with
+    /// their DDL, without applying them.
+    #[arg(long, conflicts_with = "dev_mode")]
+    pub dry_run: bool,
+
+    /// Together with `--dry-run`, also apply the migrations in a transaction
+    /// that is always rolled back, to check that they succeed.
+    #[arg(long, requires = "dry_run")]
+    pub rollback: bool,
 }
 
 #[derive(clap::Args, Clone, Debug)]
@@ -135,10 +186,75 @@ pub struct ShowStatus {
     pub cfg: MigrationConfig,
 
     /// Do not print any messages, only indicate success by exit status.
+    #[arg(long, conflicts_with = "json")]
+    pub quiet: bool,
+
+    /// Print migration status as JSON. Exit status is zero regardless of
+    /// whether the database is up to date.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct MigrationDiff {
+    #[command(flatten)]
+    pub cfg: MigrationConfig,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct MigrationLint {
+    #[command(flatten)]
+    pub cfg: MigrationConfig,
+
+    /// Migration files to check. Defaults to all migrations in
+    /// the schema directory.
+    #[arg(value_hint=ValueHint::FilePath)]
+    pub files: Vec<PathBuf>,
+
+    /// Print findings as a JSON array, for use in CI annotations.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct MigrationRevert {
+    #[command(flatten)]
+    pub cfg: MigrationConfig,
+
+    /// Revision to revert the database to. A unique revision prefix can
+    /// be specified instead of a full revision name. Use `initial` to
+    /// revert all migrations.
+    #[arg(long, value_name = "REV")]
+    pub to: String,
+
+    /// Do not ask for confirmation. Statements that drop data are still
+    /// printed.
+    #[arg(long)]
+    pub non_interactive: bool,
+
+    /// Do not print messages, only indicate success by exit status
     #[arg(long)]
     pub quiet: bool,
 }
 
+#[derive(clap::Args, Clone, Debug)]
+pub struct MigrationTest {
+    #[command(flatten)]
+    pub cfg: MigrationConfig,
+
+    /// Revision at which fixtures are loaded. Migrations up to and
+    /// including this revision are applied before the fixtures, the rest
+    /// after them. A unique revision prefix can be specified instead of a
+    /// full revision name. Defaults to the last revision.
+    #[arg(long, value_name = "REV")]
+    pub fixtures_at: Option<String>,
+
+    /// EdgeQL file with fixture data. Can be specified multiple times.
+    /// Defaults to all `.edgeql` files in <schema-dir>/tests/fixtures.
+    #[arg(long, value_hint=ValueHint::FilePath)]
+    pub fixture: Vec<PathBuf>,
+}
+
 #[derive(clap::Args, Clone, Debug)]
 pub struct MigrationLog {
     #[command(flatten)]
@@ -162,6 +278,17 @@ pub struct MigrationLog {
     /// Show maximum N revisions (default: no limit).
     #[arg(long)]
     pub limit: Option<usize>,
+
+    /// Print revisions as JSON, including parent revision, message and
+    /// file path of each migration.
+    #[arg(long)]
+    pub json: bool,
+
+    /// Draw revisions from the filesystem and the database as a graph,
+    /// newest first, showing where the two histories diverge. Also marks
+    /// the database head, dev mode migrations, fixups and squash points.
+    #[arg(long, conflicts_with_all = ["json", "from_fs", "from_db"])]
+    pub graph: bool,
 }
 
 #[derive(clap::Args, Clone, Debug)]
@@ -185,35 +312,53 @@ pub struct UpgradeCheck {
     /// Check upgrade to a specified version.
     #[arg(long)]
     #[arg(conflicts_with_all=&[
-        "to_testing", "to_nightly", "to_channel",
+        "to_testing", "to_nightly", "to_channel", "matrix",
     ])]
     pub to_version: Option<ver::Filter>,
 
     /// Check upgrade to latest nightly version.
     #[arg(long)]
     #[arg(conflicts_with_all=&[
-        "to_version", "to_testing", "to_channel",
+        "to_version", "to_testing", "to_channel", "matrix",
     ])]
     pub to_nightly: bool,
 
     /// Check upgrade to latest testing version.
     #[arg(long)]
     #[arg(conflicts_with_all=&[
-        "to_version", "to_nightly", "to_channel",
+        "to_version", "to_nightly", "to_channel", "matrix",
     ])]
     pub to_testing: bool,
 
     /// Check upgrade to latest version in the channel.
     #[arg(long, value_enum)]
     #[arg(conflicts_with_all=&[
-        "to_version", "to_nightly", "to_testing",
+        "to_version", "to_nightly", "to_testing", "matrix",
     ])]
     pub to_channel: Option<Channel>,
 
+    /// Check upgrade to each of the comma-separated versions or channels
+    /// (`stable`, `testing`, `nightly`), e.g. `--matrix=5,stable,nightly`.
+    #[arg(long, value_name = "TARGETS")]
+    pub matrix: Option<String>,
+
+    /// Also compile `.edgeql` files matching the glob against the upgraded
+    /// schema. Reports queries that no longer compile or whose output shape
+    /// differs from the one on the current instance.
+    #[arg(long, value_name = "GLOB")]
+    pub queries: Option<String>,
+
     /// Monitor schema changes and check again on change.
     #[arg(long)]
+    #[arg(conflicts_with_all=&["json", "matrix"])]
     pub watch: bool,
 
+    /// Print results as JSON: the target version, schema errors with
+    /// file positions, the result of applying each migration and timings.
+    /// With `--matrix`, an array with a result per target is printed.
+    #[arg(long)]
+    pub json: bool,
+
     #[arg(hide = true)]
     pub run_server_with_status: Option<PathBuf>,
 }
@@ -1,5 +1,5 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str;
 
 use codespan_reporting::diagnostic::{Diagnostic, Label, LabelStyle};
@@ -45,6 +45,40 @@ fn get_error_info<'x>(
     Some(res)
 }
 
+/// Position of a migration error in a schema file
+#[derive(Debug, Clone)]
+pub struct ErrorLocation {
+    pub path: PathBuf,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, in characters
+    pub column: usize,
+    /// Byte offsets in the file
+    pub start: usize,
+    pub end: usize,
+}
+
+pub fn error_location(err: &Error, source_map: &SourceMap<SourceName>) -> Option<ErrorLocation> {
+    let (path, data, start, end, _) = get_error_info(err, source_map)?;
+    let (line, column) = line_column(&data, start);
+    Some(ErrorLocation {
+        path: path.to_path_buf(),
+        line,
+        column,
+        start,
+        end,
+    })
+}
+
+pub fn line_column(data: &str, offset: usize) -> (usize, usize) {
+    let before = data.get(..offset).unwrap_or(data);
+    let line_start = before.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
 pub fn print_migration_error(
     err: &Error,
     source_map: &SourceMap<SourceName>,
@@ -63,13 +63,28 @@ pub struct RebaseMigrations {
 }
 
 impl RebaseMigrations {
+    /// Name of the last migration shared by both branches
+    pub fn last_common(&self) -> Option<&str> {
+        self.base_migrations.last().map(|v| v.0.as_str())
+    }
+
+    /// Number of migrations shared by both branches
+    pub fn base_count(&self) -> usize {
+        self.base_migrations.len()
+    }
+
+    /// Migrations present only in 'source'
+    pub fn source_only(&self) -> impl Iterator<Item = &DBMigration> {
+        self.source_migrations.values()
+    }
+
+    /// Migrations present only in 'target'
+    pub fn target_only(&self) -> impl Iterator<Item = &DBMigration> {
+        self.target_migrations.values()
+    }
+
     pub fn print_status(&self) {
-        let last_common = self
-            .base_migrations
-            .last()
-            .map(|v| v.0.as_str())
-            .unwrap_or("initial")
-            .green();
+        let last_common = self.last_common().unwrap_or("initial").green();
 
         let format_migration_on_length = |c: usize| {
             if c > 1 {
@@ -157,9 +172,41 @@ pub async fn get_diverging_migrations(
     source: &mut Connection,
     target: &mut Connection,
 ) -> anyhow::Result<RebaseMigrations> {
-    let mut source_migrations = read_all(source, true, false).await?;
-    let mut target_migrations = read_all(target, true, false).await?;
+    let migrations = split_migrations(source, target).await?;
+    if migrations.target_migrations.is_empty() && !migrations.base_migrations.is_empty() {
+        // target is up to date with source
+        anyhow::bail!("Branch {} is already up-to-date", target.database())
+    }
+    Ok(migrations)
+}
+
+/// Splits migrations of both branches into the common part and
+/// the migrations unique to each of them.
+pub async fn split_migrations(
+    source: &mut Connection,
+    target: &mut Connection,
+) -> anyhow::Result<RebaseMigrations> {
+    let source_migrations = read_branch_migrations(source).await?;
+    let target_migrations = read_branch_migrations(target).await?;
+    split_migration_lists(source_migrations, target_migrations)
+}
+
+/// Migrations of a branch, so they can be split against several branches
+/// without reading them again.
+#[derive(Clone)]
+pub(crate) struct BranchMigrations(IndexMap<String, DBMigration>);
 
+pub(crate) async fn read_branch_migrations(
+    cli: &mut Connection,
+) -> anyhow::Result<BranchMigrations> {
+    Ok(BranchMigrations(read_all(cli, true, false).await?))
+}
+
+/// Same as `split_migrations` for the migrations that are already read.
+pub(crate) fn split_migration_lists(
+    BranchMigrations(mut source_migrations): BranchMigrations,
+    BranchMigrations(mut target_migrations): BranchMigrations,
+) -> anyhow::Result<RebaseMigrations> {
     if source_migrations.is_empty() {
         return Ok(RebaseMigrations {
             base_migrations: IndexMap::new(),
@@ -170,11 +217,6 @@ pub async fn get_diverging_migrations(
 
     for (index, (id, _)) in target_migrations.iter().enumerate().rev() {
         if source_migrations.contains_key(id) {
-            if index == target_migrations.len() - 1 {
-                // target is up to date with source
-                anyhow::bail!("Branch {} is already up-to-date", target.database())
-            }
-
             let source_index = source_migrations
                 .get_index_of(id)
                 .context("Expected source_migrations to contain ID")?;
@@ -262,6 +304,7 @@ pub async fn do_rebase(
     let temp_ctx = Context {
         schema_dir: temp_dir.path().to_path_buf(),
         edgedb_version: None,
+        migrations: Default::default(),
         quiet: false,
     };
 
@@ -0,0 +1,293 @@
+use colorful::Colorful;
+use edgeql_parser::tokenizer::{Kind, Tokenizer};
+use indexmap::IndexMap;
+
+use crate::async_try;
+use crate::bug;
+use crate::commands::{ExitCode, Options};
+use crate::connect::Connection;
+use crate::migrations::context::Context;
+use crate::migrations::db_migration::{self, DBMigration};
+use crate::migrations::diff::{collect_diff, print_diff, SchemaDiff};
+use crate::migrations::edb::{execute, execute_if_connected};
+use crate::migrations::migrate::{apply_migrations_inner, schema_at, Operation};
+use crate::migrations::migration::{self, MigrationFile};
+use crate::migrations::options::MigrationRevert;
+use crate::migrations::timeout;
+use crate::migrations::NULL_MIGRATION;
+use crate::portable::exit_codes;
+use crate::print;
+use crate::question;
+
+pub async fn revert(
+    cli: &mut Connection,
+    options: &Options,
+    params: &MigrationRevert,
+) -> anyhow::Result<()> {
+    let old_state = cli.set_ignore_error_state();
+    let res = _revert(cli, options, params).await;
+    cli.restore_state(old_state);
+    res
+}
+
+async fn _revert(
+    cli: &mut Connection,
+    _options: &Options,
+    params: &MigrationRevert,
+) -> anyhow::Result<()> {
+    let ctx = Context::from_project_or_config(&params.cfg, params.quiet).await?;
+    let migrations = migration::read_all(&ctx, true).await?;
+    let db_migrations = db_migration::read_all(cli, false, true).await?;
+
+    let Some(target) = find_target(&db_migrations, &params.to)? else {
+        if !params.quiet {
+            print::success(format!(
+                "Database is already at revision {}.",
+                db_migrations
+                    .last()
+                    .map(|(id, _)| &id[..])
+                    .unwrap_or(NULL_MIGRATION),
+            ));
+        }
+        return Ok(());
+    };
+    let history = target_history(&ctx, &migrations, &db_migrations, target)?;
+
+    let (schema, diff) = revert_diff(cli, history).await?;
+    if !params.quiet {
+        print_diff(&diff);
+    }
+    if diff.incomplete {
+        anyhow::bail!(
+            "cannot revert automatically: some of the changes require \
+            user input. Check out the target revision of the schema and \
+            run `edgedb migration create` instead."
+        );
+    }
+    let dropping = dropping_statements(&diff);
+    if !dropping.is_empty() {
+        print::warn(
+            "The following statements drop schema objects. \
+            Data stored in them will be lost:",
+        );
+        for statement in &dropping {
+            eprintln!("  {}", statement.lines().next().unwrap_or_default());
+        }
+    }
+
+    if !params.non_interactive {
+        let target = target.unwrap_or(NULL_MIGRATION).to_string();
+        let q = if dropping.is_empty() {
+            question::Confirm::new(format!("Revert the database to revision {target}?"))
+        } else {
+            question::Confirm::new_dangerous(format!(
+                "Revert the database to revision {target}, losing data?"
+            ))
+        };
+        if !cli.ping_while(q.async_ask()).await? {
+            print::error("Canceled.");
+            return Err(ExitCode::new(exit_codes::NOT_CONFIRMED))?;
+        }
+    }
+
+    apply_revert(cli, &schema, history, &diff).await?;
+    if !params.quiet {
+        let target = target.unwrap_or(NULL_MIGRATION);
+        if print::use_color() {
+            eprintln!(
+                "{} {}",
+                "Reverted to revision".bold().light_green(),
+                target.bold().white(),
+            );
+        } else {
+            eprintln!("Reverted to revision {}", target);
+        }
+    }
+    Ok(())
+}
+
+/// Resolves the revision prefix among the applied migrations.
+///
+/// Returns `Ok(None)` if the database is already at the target revision
+/// and `Ok(Some(None))` if all migrations should be reverted.
+fn find_target<'a>(
+    db_migrations: &'a IndexMap<String, DBMigration>,
+    prefix: &str,
+) -> anyhow::Result<Option<Option<&'a str>>> {
+    let last = db_migrations.last().map(|(id, _)| &id[..]);
+    if prefix == NULL_MIGRATION {
+        return Ok(last.map(|_| None));
+    }
+    let mut found = db_migrations.keys().filter(|id| id.starts_with(prefix));
+    let target = match (found.next(), found.next()) {
+        (Some(target), None) => &target[..],
+        (Some(_), Some(_)) => {
+            anyhow::bail!("More than one revision matches prefix {:?}", prefix);
+        }
+        (None, _) => {
+            anyhow::bail!("No applied revision with prefix {:?} found", prefix);
+        }
+    };
+    if Some(target) == last {
+        return Ok(None);
+    }
+    Ok(Some(Some(target)))
+}
+
+/// Returns migration files that make up the history up to and including
+/// `target`, checking that it matches the history applied to the database.
+fn target_history<'a>(
+    ctx: &Context,
+    migrations: &'a IndexMap<String, MigrationFile>,
+    db_migrations: &IndexMap<String, DBMigration>,
+    target: Option<&str>,
+) -> anyhow::Result<&'a indexmap::map::Slice<String, MigrationFile>> {
+    let len = match target {
+        Some(target) => {
+            let idx = migrations.get_index_of(target).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "revision {} is not found in {:?}",
+                    target,
+                    ctx.schema_dir.join("migrations"),
+                )
+            })?;
+            idx + 1
+        }
+        None => 0,
+    };
+    let history = migrations
+        .get_range(..len)
+        .ok_or_else(|| bug::error("range slicing error"))?;
+    if !history.keys().eq(db_migrations.keys().take(len)) {
+        anyhow::bail!(
+            "database applied migration history diverges from \
+            migration history in {:?} before revision {}",
+            ctx.schema_dir.join("migrations"),
+            target.unwrap_or(NULL_MIGRATION),
+        );
+    }
+    Ok(history)
+}
+
+/// Computes the target schema (as SDL) and the DDL needed to go from
+/// the current schema to it. Nothing is committed.
+async fn revert_diff(
+    cli: &mut Connection,
+    history: &indexmap::map::Slice<String, MigrationFile>,
+) -> anyhow::Result<(String, SchemaDiff)> {
+    let old_timeout = timeout::inhibit_for_transaction(cli).await?;
+    async_try! {
+        async {
+            let schema = schema_at(cli, history).await?;
+            execute(cli, format!("START MIGRATION TO {{ {} }};", schema)).await?;
+            let diff = async_try! {
+                async {
+                    collect_diff(cli).await
+                },
+                finally async {
+                    execute_if_connected(cli, "ABORT MIGRATION").await
+                }
+            }?;
+            anyhow::Ok((schema, diff))
+        },
+        finally async {
+            timeout::restore_for_transaction(cli, old_timeout).await
+        }
+    }
+}
+
+/// Migrates the schema back using the same statements as in the confirmed
+/// `diff`, then replaces the migration history with `history`. Both steps
+/// are done in a single transaction.
+async fn apply_revert(
+    cli: &mut Connection,
+    schema: &str,
+    history: &indexmap::map::Slice<String, MigrationFile>,
+    confirmed: &SchemaDiff,
+) -> anyhow::Result<()> {
+    let old_timeout = timeout::inhibit_for_transaction(cli).await?;
+    async_try! {
+        async {
+            execute(cli, "START TRANSACTION").await?;
+            async_try! {
+                async {
+                    execute(cli, format!("START MIGRATION TO {{ {} }};", schema)).await?;
+                    let diff = collect_diff(cli).await?;
+                    if diff.incomplete || statements(&diff) != statements(confirmed) {
+                        anyhow::bail!(
+                            "schema has changed since the revert was confirmed, \
+                            please run the command again"
+                        );
+                    }
+                    execute(cli, "COMMIT MIGRATION").await?;
+                    // The schema now matches the one produced by `history`,
+                    // so the history can be rewritten
+                    let operations = vec![Operation::Rewrite(history)];
+                    apply_migrations_inner(cli, &operations, true).await
+                },
+                except async {
+                    execute_if_connected(cli, "ROLLBACK").await
+                },
+                else async {
+                    execute(cli, "COMMIT").await
+                }
+            }
+        },
+        finally async {
+            timeout::restore_for_transaction(cli, old_timeout).await
+        }
+    }
+}
+
+fn statements(diff: &SchemaDiff) -> Vec<&str> {
+    diff.confirmed
+        .iter()
+        .chain(diff.proposed.iter().flat_map(|c| &c.statements))
+        .map(|s| &s[..])
+        .collect()
+}
+
+fn dropping_statements(diff: &SchemaDiff) -> Vec<&str> {
+    statements(diff)
+        .into_iter()
+        .filter(|s| drops_data(s))
+        .collect()
+}
+
+/// Returns whether the DDL drops a type, property or link, either at the
+/// top level or inside of `ALTER` blocks, so data stored in it is lost.
+fn drops_data(ddl: &str) -> bool {
+    let mut statement_start = true;
+    let mut after_drop = false;
+    for token in Tokenizer::new(ddl).map_while(Result::ok) {
+        let word = token.text.to_lowercase();
+        if after_drop && matches!(&word[..], "type" | "property" | "link") {
+            return true;
+        }
+        after_drop = statement_start && word == "drop";
+        statement_start = matches!(
+            token.kind,
+            Kind::Semicolon | Kind::OpenBrace | Kind::CloseBrace
+        );
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::drops_data;
+
+    #[test]
+    fn dropping_statements() {
+        assert!(drops_data("DROP TYPE default::User;"));
+        assert!(drops_data(
+            "ALTER TYPE default::User { ALTER LINK friends { DROP PROPERTY since; }; };"
+        ));
+        assert!(!drops_data(
+            "ALTER TYPE default::User { DROP ANNOTATION title; };"
+        ));
+        assert!(!drops_data(
+            "ALTER TYPE default::User { CREATE PROPERTY note := 'DROP TYPE x'; };"
+        ));
+    }
+}
@@ -1,8 +1,9 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io;
 use std::path::{Path, PathBuf};
 
 use anyhow::Context as _;
+use indexmap::IndexMap;
 use tokio::fs;
 
 use crate::async_try;
@@ -10,13 +11,16 @@ use crate::bug;
 use crate::commands::{ExitCode, Options};
 use crate::connect::Connection;
 use crate::migrations::context::Context;
-use crate::migrations::create::{execute_start_migration, write_migration};
+use crate::migrations::create::{add_metadata, execute_start_migration, write_migration};
 use crate::migrations::create::{first_migration, normal_migration};
+use crate::migrations::create::{run_interactive, run_non_interactive};
 use crate::migrations::create::{CurrentMigration, MigrationToText};
 use crate::migrations::create::{FutureMigration, MigrationKey};
 use crate::migrations::edb::{execute, execute_if_connected};
-use crate::migrations::migration;
+use crate::migrations::migrate::{apply_migration, schema_at};
+use crate::migrations::migration::{self, MigrationFile};
 use crate::migrations::options::CreateMigration;
+use crate::migrations::rebase::fix_migration_ids;
 use crate::migrations::status::migrations_applied;
 use crate::migrations::timeout;
 use crate::print::{echo, Highlight};
@@ -34,6 +38,9 @@ pub async fn main(
 ) -> anyhow::Result<()> {
     let ctx = Context::from_project_or_config(&create.cfg, create.non_interactive).await?;
     let migrations = migration::read_all(&ctx, true).await?;
+    if create.from.is_some() || create.to.is_some() {
+        return squash_range(cli, &ctx, create, &migrations).await;
+    }
     let Some(db_rev) = migrations_applied(cli, &ctx, &migrations).await? else {
         return Err(ExitCode::new(3).into());
     };
@@ -52,6 +59,7 @@ pub async fn main(
     }
 
     let squashed = create_revision(cli, &ctx, create).await?;
+    let squashed = add_metadata(&ctx, create, squashed);
 
     let key = MigrationKey::Fixup {
         target_revision: squashed.id()?.to_owned(),
@@ -79,6 +87,201 @@ pub async fn main(
     Ok(())
 }
 
+async fn squash_range(
+    cli: &mut Connection,
+    ctx: &Context,
+    create: &CreateMigration,
+    migrations: &IndexMap<String, MigrationFile>,
+) -> anyhow::Result<()> {
+    let from = match &create.from {
+        Some(prefix) => find_revision(migrations, prefix)?,
+        None => 0,
+    };
+    let to = match &create.to {
+        Some(prefix) => find_revision(migrations, prefix)?,
+        None => migrations.len().saturating_sub(1),
+    };
+    if to <= from {
+        anyhow::bail!(
+            "Nothing to squash: the range must contain at least two revisions \
+             and `--from` must precede `--to`."
+        );
+    }
+    let range = |end: usize| {
+        migrations
+            .get_range(..end)
+            .ok_or_else(|| bug::error("range slicing error"))
+    };
+    let (from_id, to_id) = (&migrations[from].data.id, &migrations[to].data.id);
+    if !create.non_interactive {
+        cli.ping_while(confirm_range(from_id, to_id, to - from + 1))
+            .await?;
+    }
+
+    // Fixups for databases within the range bring them to the schema of
+    // the last squashed revision, which may well be empty
+    let create = &CreateMigration {
+        allow_empty: true,
+        ..create.clone()
+    };
+    let old_timeout = timeout::inhibit_for_transaction(cli).await?;
+    let (squashed, mut fixups) = async_try! {
+        async {
+            let schema = schema_at(cli, range(to + 1)?).await?;
+            let key = MigrationKey::Index(from as u64 + 1);
+            let squashed =
+                migration_onto(cli, ctx, create, range(from)?, &schema, key).await?;
+            let squashed = add_metadata(ctx, create, squashed);
+            let mut fixups = Vec::new();
+            for idx in from..to {
+                let key = MigrationKey::Fixup {
+                    target_revision: squashed.id()?.to_owned(),
+                };
+                let history = range(idx + 1)?;
+                fixups.push(migration_onto(cli, ctx, create, history, &schema, key).await?);
+            }
+            anyhow::Ok((squashed, fixups))
+        },
+        finally async {
+            timeout::restore_for_transaction(cli, old_timeout).await
+        }
+    }?;
+    let squashed_id = squashed.id()?.to_owned();
+    fixups.push(FutureMigration::empty(
+        MigrationKey::Fixup {
+            target_revision: squashed_id.clone(),
+        },
+        to_id,
+    ));
+
+    let mut drop = TwoStageRemove::new(ctx);
+    for migration in migrations[from..=to].values() {
+        drop.rename(&migration.path).await?;
+    }
+    write_migration(ctx, &squashed, false).await?;
+    // Later revisions are renumbered to keep file numbers consecutive and
+    // the first one is pointed at the squashed revision. Their ids are
+    // then recomputed by `fix_migration_ids`.
+    for (n, (id, migration)) in migrations[to + 1..].iter().enumerate() {
+        let mut text = fs::read_to_string(&migration.path).await?;
+        if n == 0 {
+            text = migration.data.replace_parent_id(&text, &squashed_id);
+        }
+        let path =
+            migration
+                .path
+                .with_file_name(format!("{:05}-{}.edgeql", from + n + 2, &id[..7]));
+        drop.rename(&migration.path).await?;
+        fs::write(&path, text).await?;
+    }
+    let mut changed_ids = HashMap::new();
+    fix_migration_ids(ctx, |old_id, new_id| {
+        changed_ids.insert(old_id.clone(), new_id.clone());
+    })
+    .await?;
+    for (id, migration) in migrations[to + 1..].iter() {
+        let new_id = changed_ids
+            .get(id)
+            .ok_or_else(|| bug::error("revision after squashed range kept its id"))?;
+        let key = MigrationKey::Fixup {
+            target_revision: new_id.clone(),
+        };
+        fixups.push(FutureMigration::empty(key, &migration.data.id));
+    }
+    // Existing fixups leading to a renumbered revision must lead to its
+    // new id instead, or databases that used them will lose their path
+    for fixup in migration::read_fixups(ctx, false).await? {
+        let Some(new_id) = fixup
+            .fixup_target
+            .as_ref()
+            .and_then(|target| changed_ids.get(target))
+        else {
+            continue;
+        };
+        let path = fixup
+            .path
+            .with_file_name(format!("{}-{}.edgeql", fixup.data.parent_id, new_id));
+        fs::rename(&fixup.path, &path).await?;
+    }
+    for (idx, (id, migration)) in migration::read_all(ctx, true).await?.iter().enumerate() {
+        let path = migration
+            .path
+            .with_file_name(format!("{:05}-{}.edgeql", idx + 1, &id[..7]));
+        if path != migration.path {
+            fs::rename(&migration.path, &path).await?;
+        }
+    }
+    for fixup in &fixups {
+        write_migration(ctx, fixup, false).await?;
+    }
+    drop.commit().await?;
+
+    echo!(
+        "Squashed",
+        to - from + 1,
+        "revisions into",
+        squashed_id.emphasize(),
+        "and created",
+        fixups.len(),
+        "fixup files."
+    );
+    print_final_message(true)?;
+    Ok(())
+}
+
+fn find_revision(
+    migrations: &IndexMap<String, MigrationFile>,
+    prefix: &str,
+) -> anyhow::Result<usize> {
+    let mut found = migrations
+        .keys()
+        .enumerate()
+        .filter(|(_, id)| id.starts_with(prefix));
+    match (found.next(), found.next()) {
+        (Some((idx, _)), None) => Ok(idx),
+        (Some(_), Some(_)) => {
+            anyhow::bail!("More than one revision matches prefix {:?}", prefix)
+        }
+        (None, _) => anyhow::bail!("No revision with prefix {:?} found", prefix),
+    }
+}
+
+/// Creates a migration from the schema produced by `history` to `schema`.
+/// Must be called with transaction timeouts inhibited.
+async fn migration_onto(
+    cli: &mut Connection,
+    ctx: &Context,
+    create: &CreateMigration,
+    history: &indexmap::map::Slice<String, MigrationFile>,
+    schema: &str,
+    key: MigrationKey,
+) -> anyhow::Result<FutureMigration> {
+    execute(cli, "START MIGRATION REWRITE").await?;
+    async_try! {
+        async {
+            for migration in history.values() {
+                apply_migration(cli, migration).await?;
+            }
+            execute(cli, format!("START MIGRATION TO {{ {} }};", schema)).await?;
+            async_try! {
+                async {
+                    if create.non_interactive {
+                        run_non_interactive(ctx, cli, key, create).await
+                    } else {
+                        run_interactive(ctx, cli, key, create).await
+                    }
+                },
+                finally async {
+                    execute_if_connected(cli, "ABORT MIGRATION").await
+                }
+            }
+        },
+        finally async {
+            execute_if_connected(cli, "ABORT MIGRATION REWRITE").await
+        }
+    }
+}
+
 async fn needs_fixup(cli: &mut Connection, ctx: &Context) -> anyhow::Result<bool> {
     execute_start_migration(ctx, cli).await?;
     async_try! {
@@ -148,6 +351,31 @@ async fn confirm_squashing(db_rev: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn confirm_range(from: &str, to: &str, count: usize) -> anyhow::Result<()> {
+    echo!(
+        "Squashing",
+        count,
+        "revisions from",
+        from.emphasize(),
+        "to",
+        to.emphasize()
+    );
+    echo!(
+        "Later revisions will be renumbered and get new ids. Fixup files \
+           will be created for databases that are at a revision within \
+           or after the range."
+    );
+    echo!(
+        "Data migrations inside the range are discarded: the squashed \
+           revision only contains the schema changes."
+    );
+    echo!("");
+    if !Confirm::new("Proceed?").async_ask().await? {
+        return Err(ExitCode::new(0))?;
+    }
+    Ok(())
+}
+
 async fn want_fixup() -> anyhow::Result<bool> {
     echo!(
         "Your schema differs from the last revision. \
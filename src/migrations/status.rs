@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use colorful::Colorful;
 use indexmap::IndexMap;
 
@@ -6,11 +8,30 @@ use crate::commands::{ExitCode, Options};
 use crate::connect::Connection;
 use crate::migrations::context::Context;
 use crate::migrations::create::{execute_start_migration, CurrentMigration};
+use crate::migrations::db_migration::{self, MigrationGeneratedBy};
 use crate::migrations::edb::execute_if_connected;
 use crate::migrations::migration::{self, MigrationFile};
 use crate::migrations::options::ShowStatus;
 use crate::print;
 
+#[derive(serde::Serialize)]
+struct StatusJson {
+    applied_revision: Option<String>,
+    last_file_revision: Option<String>,
+    database_revision_in_files: bool,
+    pending_migrations: Vec<PendingMigration>,
+    /// Unknown (`null`) unless all migrations are applied
+    has_schema_diff: Option<bool>,
+    dev_mode_migrations: Vec<String>,
+    up_to_date: bool,
+}
+
+#[derive(serde::Serialize)]
+struct PendingMigration {
+    id: String,
+    path: PathBuf,
+}
+
 async fn ensure_diff_is_empty(cli: &mut Connection, ctx: &Context) -> Result<(), anyhow::Error> {
     let data = cli
         .query_required_single::<CurrentMigration, _>("DESCRIBE CURRENT MIGRATION AS JSON", &())
@@ -47,6 +68,9 @@ pub async fn status(
     _options: &Options,
     status: &ShowStatus,
 ) -> Result<(), anyhow::Error> {
+    if status.json {
+        return json_or_error(status_json(cli, status).await);
+    }
     let ctx = Context::from_project_or_config(&status.cfg, status.quiet).await?;
     let migrations = migration::read_all(&ctx, true).await?;
     match up_to_date_check(cli, &ctx, &migrations).await? {
@@ -67,12 +91,97 @@ pub async fn status(
     }
 }
 
-pub async fn migrations_applied(
-    cli: &mut Connection,
-    ctx: &Context,
-    migrations: &IndexMap<String, MigrationFile>,
-) -> Result<Option<String>, anyhow::Error> {
-    let db_migration: Option<String> = cli
+#[derive(serde::Serialize)]
+struct ErrorJson {
+    error: String,
+}
+
+/// Runs `migration status --json` including the connection, so that
+/// connection errors are reported as JSON too
+#[tokio::main(flavor = "current_thread")]
+pub async fn status_json_main(options: &Options, status: &ShowStatus) -> anyhow::Result<()> {
+    json_or_error(
+        async {
+            let mut cli = options.conn_params.connect().await?;
+            status_json(&mut cli, status).await
+        }
+        .await,
+    )
+}
+
+fn json_or_error(result: anyhow::Result<()>) -> anyhow::Result<()> {
+    let Err(e) = result else {
+        return Ok(());
+    };
+    let error = ErrorJson {
+        error: format!("{e:#}"),
+    };
+    println!("{}", serde_json::to_string_pretty(&error)?);
+    Err(ExitCode::new(1).into())
+}
+
+async fn status_json(cli: &mut Connection, status: &ShowStatus) -> Result<(), anyhow::Error> {
+    let ctx = Context::from_project_or_config(&status.cfg, true).await?;
+    let migrations = migration::read_all(&ctx, true).await?;
+    let applied_revision = last_db_migration(cli).await?;
+    let last_file_revision = migrations.keys().last().cloned();
+    let database_revision_in_files = match &applied_revision {
+        Some(rev) => migrations.contains_key(rev),
+        None => true,
+    };
+    let pending_migrations = migrations
+        .iter()
+        .skip(match &applied_revision {
+            Some(rev) => migrations.get_index_of(rev).map(|i| i + 1).unwrap_or(0),
+            None => 0,
+        })
+        .filter(|_| database_revision_in_files)
+        .map(|(id, file)| PendingMigration {
+            id: id.clone(),
+            path: file.path.clone(),
+        })
+        .collect::<Vec<_>>();
+    let has_schema_diff = if applied_revision == last_file_revision {
+        execute_start_migration(&ctx, cli).await?;
+        let data = async_try! {
+            async {
+                cli.query_required_single::<CurrentMigration, _>(
+                    "DESCRIBE CURRENT MIGRATION AS JSON",
+                    &(),
+                ).await
+            },
+            finally async {
+                execute_if_connected(cli, "ABORT MIGRATION").await
+            }
+        }?;
+        Some(!data.confirmed.is_empty() || !data.complete)
+    } else {
+        None
+    };
+    let dev_mode_migrations = db_migration::read_all(cli, false, true)
+        .await?
+        .into_values()
+        .filter(|m| matches!(m.generated_by, Some(MigrationGeneratedBy::DevMode)))
+        .map(|m| m.name)
+        .collect();
+    let up_to_date = has_schema_diff == Some(false);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&StatusJson {
+            applied_revision,
+            last_file_revision,
+            database_revision_in_files,
+            pending_migrations,
+            has_schema_diff,
+            dev_mode_migrations,
+            up_to_date,
+        })?
+    );
+    Ok(())
+}
+
+async fn last_db_migration(cli: &mut Connection) -> Result<Option<String>, anyhow::Error> {
+    let db_migration = cli
         .query_single(
             r###"
             WITH Last := (SELECT schema::Migration
@@ -82,6 +191,15 @@ pub async fn migrations_applied(
             &(),
         )
         .await?;
+    Ok(db_migration)
+}
+
+pub async fn migrations_applied(
+    cli: &mut Connection,
+    ctx: &Context,
+    migrations: &IndexMap<String, MigrationFile>,
+) -> Result<Option<String>, anyhow::Error> {
+    let db_migration = last_db_migration(cli).await?;
     if db_migration.as_ref() != migrations.keys().last() {
         if !ctx.quiet {
             if let Some(db_migration) = &db_migration {
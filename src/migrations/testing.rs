@@ -0,0 +1,181 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use colorful::Colorful;
+use indexmap::IndexMap;
+use tokio::fs;
+
+use crate::async_try;
+use crate::branch::connections::create_temp_branch;
+use crate::bug;
+use crate::commands::{ExitCode, Options};
+use crate::connect::Connection;
+use crate::error_display::print_query_error;
+use crate::migrations::context::Context;
+use crate::migrations::edb::{execute, execute_if_connected};
+use crate::migrations::migrate::apply_migrations;
+use crate::migrations::migration::{self, MigrationFile};
+use crate::migrations::options::MigrationTest;
+use crate::migrations::NULL_MIGRATION;
+use crate::print;
+
+pub async fn test(
+    cli: &mut Connection,
+    options: &Options,
+    params: &MigrationTest,
+) -> anyhow::Result<()> {
+    let ctx = Context::from_project_or_config(&params.cfg, false).await?;
+    let migrations = migration::read_all(&ctx, true).await?;
+    let split = match &params.fixtures_at {
+        Some(prefix) => fixtures_position(&migrations, prefix)?,
+        None => migrations.len(),
+    };
+    let tests_dir = ctx.schema_dir.join("tests");
+    let fixtures = if params.fixture.is_empty() {
+        edgeql_files(&tests_dir.join("fixtures")).await?
+    } else {
+        params.fixture.clone()
+    };
+    let tests = edgeql_files(&tests_dir).await?;
+    if tests.is_empty() {
+        print::warn(format!("No tests found in {:?}.", tests_dir));
+    }
+
+    let mut branch = create_temp_branch(options, cli).await?;
+    branch.connection.set_ignore_error_state();
+    let result = run(
+        &mut branch.connection,
+        &ctx,
+        &migrations,
+        split,
+        &fixtures,
+        &tests,
+    )
+    .await;
+    branch.clean().await?;
+
+    let failed = result?;
+    if failed > 0 {
+        print::error(format!("{} of {} tests failed.", failed, tests.len()));
+        return Err(ExitCode::new(1))?;
+    }
+    print::success(format!("{} tests passed.", tests.len()));
+    Ok(())
+}
+
+/// Returns the number of migrations applied before fixtures are loaded.
+fn fixtures_position(
+    migrations: &IndexMap<String, MigrationFile>,
+    prefix: &str,
+) -> anyhow::Result<usize> {
+    if prefix == NULL_MIGRATION {
+        return Ok(0);
+    }
+    let mut found = migrations
+        .keys()
+        .enumerate()
+        .filter(|(_, id)| id.starts_with(prefix));
+    match (found.next(), found.next()) {
+        (Some((idx, _)), None) => Ok(idx + 1),
+        (Some(_), Some(_)) => {
+            anyhow::bail!("More than one revision matches prefix {:?}", prefix)
+        }
+        (None, _) => anyhow::bail!("No revision with prefix {:?} found", prefix),
+    }
+}
+
+/// Returns `.edgeql` files in the directory (not recursively), sorted by
+/// name. A missing directory has no files.
+async fn edgeql_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => Err(e).context(format!("cannot read {:?}", dir))?,
+    };
+    let mut paths = Vec::new();
+    while let Some(item) = entries.next_entry().await? {
+        let fname = item.file_name();
+        let lossy_name = fname.to_string_lossy();
+        if !lossy_name.starts_with('.')
+            && lossy_name.ends_with(".edgeql")
+            && item.file_type().await?.is_file()
+        {
+            paths.push(item.path());
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Applies migrations and fixtures, then runs tests. Returns the number
+/// of failed tests.
+async fn run(
+    cli: &mut Connection,
+    ctx: &Context,
+    migrations: &IndexMap<String, MigrationFile>,
+    split: usize,
+    fixtures: &[PathBuf],
+    tests: &[PathBuf],
+) -> anyhow::Result<usize> {
+    let before = migrations
+        .get_range(..split)
+        .ok_or_else(|| bug::error("range slicing error"))?;
+    let after = migrations
+        .get_range(split..)
+        .ok_or_else(|| bug::error("range slicing error"))?;
+    apply_migrations(cli, before, ctx, false).await?;
+    for path in fixtures {
+        let text = read_file(path).await?;
+        if let Err(err) = cli.execute(&text, &()).await {
+            print_query_error(&err, &text, false, &path.display().to_string())?;
+            anyhow::bail!("cannot load fixture {:?}", path);
+        }
+        if !ctx.quiet {
+            eprintln!("Loaded fixture {}", path.display());
+        }
+    }
+    apply_migrations(cli, after, ctx, false).await?;
+
+    let mut failed = 0;
+    for path in tests {
+        let text = read_file(path).await?;
+        // Each test runs in its own transaction, so tests modifying data
+        // don't affect each other
+        execute(cli, "START TRANSACTION").await?;
+        let result = async_try! {
+            async {
+                anyhow::Ok(cli.execute(&text, &()).await)
+            },
+            finally async {
+                execute_if_connected(cli, "ROLLBACK").await
+            }
+        }?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        match result {
+            Ok(_) => {
+                if print::use_color() {
+                    eprintln!("{} {}", "PASS".bold().light_green(), name);
+                } else {
+                    eprintln!("PASS {}", name);
+                }
+            }
+            Err(err) => {
+                failed += 1;
+                if print::use_color() {
+                    eprintln!("{} {}", "FAIL".bold().light_red(), name);
+                } else {
+                    eprintln!("FAIL {}", name);
+                }
+                print_query_error(&err, &text, false, &path.display().to_string())?;
+            }
+        }
+    }
+    Ok(failed)
+}
+
+async fn read_file(path: &Path) -> anyhow::Result<String> {
+    fs::read_to_string(path)
+        .await
+        .with_context(|| format!("cannot read {:?}", path))
+}
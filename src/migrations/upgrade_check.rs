@@ -1,30 +1,47 @@
-use std::path::Path;
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 use anyhow::Context as _;
+use edgedb_errors::{Error, ProtocolEncodingError, QueryError};
+use edgedb_protocol::client_message::{Cardinality, CompilationOptions, IoFormat};
+use edgedb_protocol::codec;
+use edgedb_protocol::common::Capabilities;
+use edgedb_protocol::descriptors::{Descriptor, TypePos};
 use edgedb_tokio::Builder;
+use indexmap::IndexMap;
 use indicatif::ProgressBar;
 use notify::{RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use regex::Regex;
 use tokio::fs;
 use tokio::sync::watch;
 
 use crate::async_try;
 use crate::commands::{ExitCode, Options};
 use crate::connect::Connection;
+use crate::error_display::print_query_error;
 use crate::migrations::context::Context;
-use crate::migrations::create::{execute_start_migration, EsdlError};
+use crate::migrations::create::{gen_start_migration, SourceName};
 use crate::migrations::edb::{execute, execute_if_connected};
-use crate::migrations::migrate::{apply_migration, ApplyMigrationError};
-use crate::migrations::migration;
+use crate::migrations::migrate::apply_migration;
+use crate::migrations::migration::{self, MigrationFile};
 use crate::migrations::options::UpgradeCheck;
+use crate::migrations::print_error::{error_location, line_column, print_migration_error};
+use crate::migrations::source_map::SourceMap;
 use crate::migrations::timeout;
 use crate::portable::config::Config;
 use crate::portable::install;
 use crate::portable::local::InstallInfo;
 use crate::portable::repository::{self, PackageInfo, Query};
+use crate::portable::ver;
 use crate::print::{echo, success, warn, Highlight};
 use crate::process;
-use crate::watch::wait_changes;
+use crate::watch::{wait_changes, STABLE_TIME};
+
+static UUID: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}").unwrap()
+});
 
 #[derive(Debug, serde::Deserialize)]
 struct EdgedbStatus {
@@ -32,16 +49,98 @@ struct EdgedbStatus {
     tls_cert_file: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 enum CheckResult {
     Okay,
     SchemaIssue,
     MigrationsIssue,
+    QueriesIssue,
+}
+
+/// Result of checking a single target version, printed by `--json`
+#[derive(Debug, Default, serde::Serialize)]
+struct Report {
+    /// Requested version or channel
+    target: String,
+    /// Exact version of the server the check was run against
+    version: Option<String>,
+    result: Option<CheckResult>,
+    /// Error that prevented the check from completing
+    error: Option<String>,
+    schema_errors: Vec<SchemaError>,
+    migrations: Vec<MigrationReport>,
+    queries: Vec<QueryReport>,
+    schema_check_ms: u64,
+    migrations_check_ms: u64,
+    queries_check_ms: u64,
+    total_ms: u64,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct SchemaError {
+    kind: String,
+    message: String,
+    hint: Option<String>,
+    file: Option<PathBuf>,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+enum MigrationStatus {
+    Applied,
+    Failed,
+    /// Not checked because an earlier migration has failed
+    Skipped,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct MigrationReport {
+    id: String,
+    file: PathBuf,
+    status: MigrationStatus,
+    error: Option<String>,
+    line: Option<usize>,
+    column: Option<usize>,
+    duration_ms: u64,
+}
+
+/// Query file checked by `--queries`
+struct QueryFile {
+    path: PathBuf,
+    text: String,
+    /// Output shape on the current instance, if it's reachable and the
+    /// query compiles there
+    shape: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+enum QueryStatus {
+    Ok,
+    Failed,
+    ShapeChanged,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct QueryReport {
+    file: PathBuf,
+    status: QueryStatus,
+    error: Option<String>,
+    line: Option<usize>,
+    column: Option<usize>,
 }
 
 #[cfg(windows)]
-pub fn upgrade_check(_options: &Options, options: &UpgradeCheck) -> anyhow::Result<()> {
+pub fn upgrade_check(cmdopt: &Options, options: &UpgradeCheck) -> anyhow::Result<()> {
     use crate::portable::windows;
 
+    if options.matrix.is_some() {
+        anyhow::bail!("`--matrix` is not supported on Windows yet");
+    }
+    let started = Instant::now();
     let status_path = tempfile::NamedTempFile::new()
         .context("tempfile failure")?
         .into_temp_path();
@@ -52,7 +151,7 @@ pub fn upgrade_check(_options: &Options, options: &UpgradeCheck) -> anyhow::Resu
         run_server_with_status: Some(windows::path_to_linux(&status_path)?.into()),
         ..options.clone()
     });
-    cmd.background_for(move || {
+    let (ctx, mut report) = cmd.background_for(move || {
         Ok(async move {
             while let Ok(meta) = fs::metadata(&status_path).await {
                 if meta.len() > "READY={}".len() as u64 {
@@ -60,31 +159,31 @@ pub fn upgrade_check(_options: &Options, options: &UpgradeCheck) -> anyhow::Resu
                 }
             }
             let ctx = Context::from_project_or_config(&options.cfg, false).await?;
+            let queries = match &options.queries {
+                Some(pattern) => read_queries(cmdopt, pattern).await?,
+                None => Vec::new(),
+            };
 
-            do_check(&ctx, &status_path, options.watch).await
+            let report =
+                do_check(&ctx, &status_path, options.watch, options.json, &queries).await?;
+            anyhow::Ok((ctx, report))
         })
-    })
+    })?;
+    report.target = target_name(options);
+    report.total_ms = millis(started);
+    finish(&ctx, vec![report], options.json, false)
 }
 
 #[cfg(unix)]
-pub fn upgrade_check(_options: &Options, options: &UpgradeCheck) -> anyhow::Result<()> {
-    let (version, _) = Query::from_options(
-        repository::QueryOptions {
-            nightly: options.to_nightly,
-            stable: false,
-            testing: options.to_testing,
-            version: options.to_version.as_ref(),
-            channel: options.to_channel,
-        },
-        || Ok(Query::stable()),
-    )?;
-
-    let pkg = repository::get_server_package(&version)?
-        .with_context(|| format!("no package matching {} found", version.display()))?;
-    let info = install::package(&pkg).context("error installing EdgeDB")?;
+pub fn upgrade_check(cmdopt: &Options, options: &UpgradeCheck) -> anyhow::Result<()> {
+    let targets = match &options.matrix {
+        Some(matrix) => parse_matrix(matrix)?,
+        None => vec![target_query(options)?],
+    };
 
     // This is run from windows to do the upgrade check
     if let Some(status_path) = &options.run_server_with_status {
+        let info = install_target(&targets[0])?;
         let server_path = info.server_path()?;
         let mut cmd = process::Native::new("edgedb", "edgedb", server_path);
         cmd.arg("--temp-dir");
@@ -95,15 +194,98 @@ pub fn upgrade_check(_options: &Options, options: &UpgradeCheck) -> anyhow::Resu
         cmd.arg("--compiler-pool-mode=on_demand");
         cmd.arg("--tls-cert-mode=generate_self_signed");
         cmd.arg("--log-level=warn");
-        cmd.exec_replacing_self()?;
-        unreachable!();
+        match cmd.exec_replacing_self()? {}
     }
 
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()?;
     let ctx = runtime.block_on(Context::from_project_or_config(&options.cfg, false))?;
-    spawn_and_check(&info, ctx, options.watch)
+    let queries = match &options.queries {
+        Some(pattern) => runtime.block_on(read_queries(cmdopt, pattern))?,
+        None => Vec::new(),
+    };
+    let mut reports = Vec::with_capacity(targets.len());
+    for query in &targets {
+        let started = Instant::now();
+        let result = install_target(query).and_then(|info| {
+            if options.matrix.is_some() && !options.json {
+                echo!("Checking upgrade to", info.version.emphasize());
+            }
+            spawn_and_check(&info, &ctx, options.watch, options.json, &queries)
+        });
+        let mut report = match result {
+            Ok(report) => report,
+            // Other targets are still checked in the matrix mode
+            Err(e) if options.matrix.is_some() => {
+                if !options.json {
+                    crate::print::error(format!("{:#}", e));
+                }
+                Report {
+                    error: Some(format!("{:#}", e)),
+                    ..Report::default()
+                }
+            }
+            Err(e) => return Err(e),
+        };
+        report.target = query.display().to_string();
+        report.total_ms = millis(started);
+        reports.push(report);
+    }
+    finish(&ctx, reports, options.json, options.matrix.is_some())
+}
+
+#[cfg(unix)]
+fn install_target(query: &Query) -> anyhow::Result<InstallInfo> {
+    let pkg = repository::get_server_package(query)?
+        .with_context(|| format!("no package matching {} found", query.display()))?;
+    install::package(&pkg).context("error installing EdgeDB")
+}
+
+fn target_query(options: &UpgradeCheck) -> anyhow::Result<Query> {
+    let (query, _) = Query::from_options(
+        repository::QueryOptions {
+            nightly: options.to_nightly,
+            stable: false,
+            testing: options.to_testing,
+            version: options.to_version.as_ref(),
+            channel: options.to_channel,
+        },
+        || Ok(Query::stable()),
+    )?;
+    Ok(query)
+}
+
+#[cfg(windows)]
+fn target_name(options: &UpgradeCheck) -> String {
+    target_query(options)
+        .map(|q| q.display().to_string())
+        .unwrap_or_default()
+}
+
+fn parse_matrix(value: &str) -> anyhow::Result<Vec<Query>> {
+    let targets = value
+        .split(',')
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(|item| match item {
+            "stable" => Ok(Query::stable()),
+            "testing" => Ok(Query::testing()),
+            "nightly" => Ok(Query::nightly()),
+            _ => item
+                .parse::<ver::Filter>()
+                .and_then(|ver| Query::from_filter(&ver))
+                .with_context(|| format!("invalid `--matrix` target {:?}", item)),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if targets.is_empty() {
+        anyhow::bail!("`--matrix` requires at least one version or channel");
+    }
+    Ok(targets)
+}
+
+fn millis(started: Instant) -> u64 {
+    started.elapsed().as_millis() as u64
 }
 
 #[cfg(windows)]
@@ -115,11 +297,18 @@ pub fn to_version(_: &PackageInfo, _: &Config) -> anyhow::Result<()> {
 pub fn to_version(pkg: &PackageInfo, config: &Config) -> anyhow::Result<()> {
     let info = install::package(pkg).context("error installing EdgeDB")?;
     let ctx = Context::for_project(config)?;
-    spawn_and_check(&info, ctx, false)
+    let report = spawn_and_check(&info, &ctx, false, false, &[])?;
+    finish(&ctx, vec![report], false, false)
 }
 
 #[cfg(unix)]
-fn spawn_and_check(info: &InstallInfo, ctx: Context, watch: bool) -> anyhow::Result<()> {
+fn spawn_and_check(
+    info: &InstallInfo,
+    ctx: &Context,
+    watch: bool,
+    json: bool,
+    queries: &[QueryFile],
+) -> anyhow::Result<Report> {
     use tokio::net::UnixDatagram;
 
     let server_path = info.server_path()?;
@@ -147,14 +336,20 @@ fn spawn_and_check(info: &InstallInfo, ctx: Context, watch: bool) -> anyhow::Res
             {}
 
             let status_file = status_dir.path().join("status");
-            do_check(&ctx, &status_file, watch).await
+            let mut report = do_check(ctx, &status_file, watch, json, queries).await?;
+            report.version = Some(info.version.to_string());
+            Ok(report)
         })
     })
 }
 
-async fn do_check(ctx: &Context, status_file: &Path, watch: bool) -> anyhow::Result<()> {
-    use CheckResult::*;
-
+async fn do_check(
+    ctx: &Context,
+    status_file: &Path,
+    watch: bool,
+    json: bool,
+    queries: &[QueryFile],
+) -> anyhow::Result<Report> {
     let status_data = fs::read_to_string(&status_file)
         .await
         .context("error reading status")?;
@@ -194,75 +389,137 @@ async fn do_check(ctx: &Context, status_file: &Path, watch: bool) -> anyhow::Res
         // dir change
         watch.watch(&ctx.schema_dir, RecursiveMode::Recursive)?;
 
-        let ok = matches!(single_check(ctx, cli).await?, Okay);
+        let ok = matches!(
+            single_check(ctx, cli, false, queries).await?.result,
+            Some(CheckResult::Okay)
+        );
         if ok {
             success("The schema is forward compatible. Ready for upgrade.");
         }
         eprintln!("Monitoring {:?} for changes.", &ctx.schema_dir);
-        watch_loop(rx, ctx, cli, ok).await?;
-        Ok(())
+        match watch_loop(rx, ctx, cli, ok, queries).await? {}
     } else {
-        match single_check(ctx, cli).await? {
-            Okay => {}
-            SchemaIssue => {
+        single_check(ctx, cli, json, queries).await
+    }
+}
+
+/// Prints the results and returns the exit code of the whole check
+fn finish(ctx: &Context, reports: Vec<Report>, json: bool, matrix: bool) -> anyhow::Result<()> {
+    use CheckResult::*;
+
+    let has = |res| reports.iter().any(|r| r.result == Some(res));
+    let exit_code = if has(SchemaIssue) {
+        Some(3)
+    } else if has(MigrationsIssue) {
+        Some(4)
+    } else if has(QueriesIssue) {
+        Some(5)
+    } else if reports.iter().any(|r| r.error.is_some()) {
+        Some(1)
+    } else {
+        None
+    };
+    if json {
+        if matrix {
+            println!("{}", serde_json::to_string_pretty(&reports)?);
+        } else {
+            println!("{}", serde_json::to_string_pretty(&reports[0])?);
+        }
+    } else if matrix {
+        echo!("Upgrade check results:");
+        for report in &reports {
+            let status = match report.result {
+                Some(Okay) => "ready for upgrade",
+                Some(SchemaIssue) => "schema is incompatible",
+                Some(MigrationsIssue) => "migrations are outdated",
+                Some(QueriesIssue) => "queries are incompatible",
+                None => "check failed",
+            };
+            let version = report.version.as_deref().unwrap_or("-");
+            echo!("  "; report.target.emphasize(), "("; version; "):", status);
+        }
+    } else {
+        match exit_code {
+            None if !ctx.quiet => {
+                echo!("The schema is forward compatible. Ready for upgrade.");
+            }
+            Some(3) => {
                 echo!("For faster feedback loop use:");
                 echo!("    edgedb migration upgrade-check --watch".command_hint());
-                return Err(ExitCode::new(3))?;
-            }
-            MigrationsIssue => {
-                // Should be no need to watch
-                return Err(ExitCode::new(4))?;
             }
+            // Should be no need to watch for migration issues
+            _ => {}
         }
-        if !ctx.quiet {
-            echo!("The schema is forward compatible. Ready for upgrade.");
-        }
-        Ok(())
+    }
+    match exit_code {
+        Some(code) => Err(ExitCode::new(code))?,
+        None => Ok(()),
     }
 }
 
-async fn single_check(ctx: &Context, cli: &mut Connection) -> anyhow::Result<CheckResult> {
+async fn single_check(
+    ctx: &Context,
+    cli: &mut Connection,
+    json: bool,
+    queries: &[QueryFile],
+) -> anyhow::Result<Report> {
     use CheckResult::*;
 
-    let bar = ProgressBar::new_spinner();
-    bar.enable_steady_tick(Duration::from_millis(100));
+    let mut report = Report::default();
+    let bar = if json {
+        ProgressBar::hidden()
+    } else {
+        let bar = ProgressBar::new_spinner();
+        bar.enable_steady_tick(Duration::from_millis(100));
+        bar
+    };
 
     bar.set_message("checking schema");
-    match execute_start_migration(ctx, cli).await {
+    let started = Instant::now();
+    let (text, source_map) = gen_start_migration(ctx).await?;
+    let result = execute(cli, text).await;
+    report.schema_check_ms = millis(started);
+    match result {
         Ok(()) => {
             execute(cli, "ABORT MIGRATION").await?;
         }
-        Err(e) if e.is::<EsdlError>() => {
-            warn(
-                "Schema incompatibilities found. \
-                  Please fix the errors above to proceed.",
-            );
-            return Ok(SchemaIssue);
+        Err(e) if e.is::<QueryError>() => {
+            bar.finish_and_clear();
+            if !json {
+                print_migration_error(&e, &source_map)?;
+                warn(
+                    "Schema incompatibilities found. \
+                      Please fix the errors above to proceed.",
+                );
+            }
+            report.schema_errors.push(schema_error(&e, &source_map));
+            report.result = Some(SchemaIssue);
+            return Ok(report);
         }
-        Err(e) => return Err(e),
+        Err(e) => return Err(e)?,
     }
 
     bar.set_message("checking migrations");
+    let started = Instant::now();
     let migrations = migration::read_all(ctx, true).await?;
     let old_timeout = timeout::inhibit_for_transaction(cli).await?;
-    async_try! {
+    report.migrations = async_try! {
         async {
             execute(cli, "START MIGRATION REWRITE").await?;
             async_try! {
                 async {
+                    let mut results = Vec::with_capacity(migrations.len());
+                    let mut failed = false;
                     for migration in migrations.values() {
-                        match apply_migration(cli, migration).await {
-                            Ok(()) => {},
-                            Err(e) if e.is::<ApplyMigrationError>() => {
-                                bar.finish_and_clear();
-                                print_apply_migration_error();
-                                return Ok(MigrationsIssue);
-                            }
-                            Err(e) => return Err(e)?,
+                        if failed {
+                            results.push(MigrationReport::new(migration, MigrationStatus::Skipped));
+                            continue;
                         }
+                        let result = check_migration(cli, migration, json).await?;
+                        failed = result.status == MigrationStatus::Failed;
+                        results.push(result);
                     }
-                    bar.finish_and_clear();
-                    anyhow::Ok(Okay)
+                    anyhow::Ok(results)
                 },
                 finally async {
                     execute_if_connected(cli, "ABORT MIGRATION REWRITE")
@@ -273,6 +530,321 @@ async fn single_check(ctx: &Context, cli: &mut Connection) -> anyhow::Result<Che
         finally async {
             timeout::restore_for_transaction(cli, old_timeout).await
         }
+    }?;
+    report.migrations_check_ms = millis(started);
+    if report
+        .migrations
+        .iter()
+        .any(|m| m.status == MigrationStatus::Failed)
+    {
+        bar.finish_and_clear();
+        if !json {
+            print_apply_migration_error();
+        }
+        report.result = Some(MigrationsIssue);
+        return Ok(report);
+    }
+
+    if !queries.is_empty() {
+        bar.set_message("checking queries");
+        let started = Instant::now();
+        let old_timeout = timeout::inhibit_for_transaction(cli).await?;
+        report.queries = async_try! {
+            async {
+                check_queries(cli, &migrations, queries, json).await
+            },
+            finally async {
+                timeout::restore_for_transaction(cli, old_timeout).await
+            }
+        }?;
+        report.queries_check_ms = millis(started);
+    }
+    bar.finish_and_clear();
+    if report.queries.iter().any(|q| q.status != QueryStatus::Ok) {
+        if !json {
+            warn(
+                "The schema and migrations are compatible, \
+                 but some of the queries need to be updated.",
+            );
+        }
+        report.result = Some(QueriesIssue);
+    } else {
+        report.result = Some(Okay);
+    }
+    Ok(report)
+}
+
+/// Compiles queries in a transaction with all the migrations applied
+async fn check_queries(
+    cli: &mut Connection,
+    migrations: &IndexMap<String, MigrationFile>,
+    queries: &[QueryFile],
+    json: bool,
+) -> anyhow::Result<Vec<QueryReport>> {
+    execute(cli, "START TRANSACTION").await?;
+    async_try! {
+        async {
+            for migration in migrations.values() {
+                apply_migration(cli, migration).await?;
+            }
+            execute(cli, "DECLARE SAVEPOINT queries").await?;
+            let mut results = Vec::with_capacity(queries.len());
+            for query in queries {
+                let result = check_query(cli, query, json).await?;
+                if result.status == QueryStatus::Failed {
+                    // Errors make the transaction unusable for next queries
+                    execute(cli, "ROLLBACK TO SAVEPOINT queries").await?;
+                }
+                results.push(result);
+            }
+            anyhow::Ok(results)
+        },
+        finally async {
+            execute_if_connected(cli, "ROLLBACK").await
+        }
+    }
+}
+
+async fn check_query(
+    cli: &mut Connection,
+    query: &QueryFile,
+    json: bool,
+) -> anyhow::Result<QueryReport> {
+    let mut report = QueryReport {
+        file: query.path.clone(),
+        status: QueryStatus::Ok,
+        error: None,
+        line: None,
+        column: None,
+    };
+    match query_shape(cli, &query.text).await {
+        Ok(shape) => {
+            if query.shape.as_ref().map_or(false, |old| old != &shape) {
+                if !json {
+                    warn(format!(
+                        "Output shape of {} has changed.",
+                        query.path.display()
+                    ));
+                }
+                report.status = QueryStatus::ShapeChanged;
+            }
+        }
+        Err(err) => {
+            if !cli.is_consistent() {
+                return Err(err)?;
+            }
+            if !json {
+                let fname = query.path.display().to_string();
+                print_query_error(&err, &query.text, false, &fname)?;
+            }
+            report.status = QueryStatus::Failed;
+            report.error = Some(err.to_string());
+            if let Some(pos) = err.position_start() {
+                let (line, column) = line_column(&query.text, pos);
+                report.line = Some(line);
+                report.column = Some(column);
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Returns cardinality and output shape of the query, see `shape_text`
+async fn query_shape(cli: &mut Connection, text: &str) -> Result<String, Error> {
+    let flags = CompilationOptions {
+        implicit_limit: None,
+        implicit_typenames: false,
+        implicit_typeids: false,
+        explicit_objectids: true,
+        allow_capabilities: Capabilities::ALL,
+        io_format: IoFormat::Binary,
+        expected_cardinality: Cardinality::Many,
+    };
+    let desc = cli.parse(&flags, text).await?;
+    let output = desc.output().map_err(ProtocolEncodingError::with_source)?;
+    Ok(format!(
+        "{:?} {}",
+        desc.result_cardinality,
+        shape_text(output.descriptors(), output.root_pos())
+    ))
+}
+
+/// Renders the output type as field names, cardinalities and scalar types.
+///
+/// Descriptors carry ids of types, which differ between instances for
+/// user-defined types, so object and custom scalar types are represented
+/// by their fields and base types respectively.
+fn shape_text(descriptors: &[Descriptor], root: Option<TypePos>) -> String {
+    match root {
+        Some(pos) => type_shape(descriptors, pos),
+        None => "nothing".into(),
+    }
+}
+
+fn type_shape(descriptors: &[Descriptor], pos: TypePos) -> String {
+    let inner = |pos: TypePos| type_shape(descriptors, pos);
+    let Some(desc) = descriptors.get(pos.0 as usize) else {
+        return "<invalid>".into();
+    };
+    match desc {
+        Descriptor::ObjectShape(obj) => {
+            let fields: Vec<_> = obj
+                .elements
+                .iter()
+                .filter(|el| !el.flag_implicit)
+                .map(|el| {
+                    let cardinality = el.cardinality.map(|c| format!("{:?} ", c));
+                    format!(
+                        "{}{}: {}{}",
+                        if el.flag_link_property { "@" } else { "" },
+                        el.name,
+                        cardinality.unwrap_or_default(),
+                        inner(el.type_pos),
+                    )
+                })
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+        Descriptor::BaseScalar(scalar) => scalar_name(&scalar.id),
+        Descriptor::Scalar(scalar) => inner(scalar.base_type_pos),
+        Descriptor::Enumeration(en) => format!("enum<{}>", en.members.join(", ")),
+        Descriptor::Set(set) => format!("set<{}>", inner(set.type_pos)),
+        Descriptor::Array(arr) => format!("array<{}>", inner(arr.type_pos)),
+        Descriptor::Range(range) => format!("range<{}>", inner(range.type_pos)),
+        Descriptor::Tuple(tuple) => {
+            let items: Vec<_> = tuple.element_types.iter().map(|p| inner(*p)).collect();
+            format!("tuple<{}>", items.join(", "))
+        }
+        Descriptor::NamedTuple(tuple) => {
+            let items: Vec<_> = tuple
+                .elements
+                .iter()
+                .map(|el| format!("{}: {}", el.name, inner(el.type_pos)))
+                .collect();
+            format!("tuple<{}>", items.join(", "))
+        }
+        // Other descriptors are not used for output, ids of user-defined
+        // types are masked just in case
+        _ => UUID
+            .replace_all(&format!("{:?}", desc), "<id>")
+            .into_owned(),
+    }
+}
+
+/// Standard scalar types have the same ids on every instance
+fn scalar_name(id: &uuid::Uuid) -> String {
+    let name = match *id {
+        codec::STD_UUID => "std::uuid",
+        codec::STD_STR => "std::str",
+        codec::STD_BYTES => "std::bytes",
+        codec::STD_INT16 => "std::int16",
+        codec::STD_INT32 => "std::int32",
+        codec::STD_INT64 => "std::int64",
+        codec::STD_FLOAT32 => "std::float32",
+        codec::STD_FLOAT64 => "std::float64",
+        codec::STD_DECIMAL => "std::decimal",
+        codec::STD_BOOL => "std::bool",
+        codec::STD_DATETIME => "std::datetime",
+        codec::STD_DURATION => "std::duration",
+        codec::STD_JSON => "std::json",
+        codec::STD_BIGINT => "std::bigint",
+        _ => return id.to_string(),
+    };
+    name.into()
+}
+
+async fn read_queries(options: &Options, pattern: &str) -> anyhow::Result<Vec<QueryFile>> {
+    let mut paths = glob::glob(pattern)
+        .with_context(|| format!("invalid glob {:?}", pattern))?
+        .collect::<Result<Vec<_>, _>>()?;
+    if paths.is_empty() {
+        anyhow::bail!("no query files match {:?}", pattern);
+    }
+    paths.sort();
+    let mut queries = Vec::with_capacity(paths.len());
+    for path in paths {
+        let text = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("cannot read {:?}", path))?;
+        queries.push(QueryFile {
+            path,
+            text,
+            shape: None,
+        });
+    }
+    // Shapes on the current instance are compared to the ones after upgrade
+    match options.conn_params.connect().await {
+        Ok(mut cli) => {
+            for query in &mut queries {
+                query.shape = query_shape(&mut cli, &query.text).await.ok();
+            }
+        }
+        Err(e) => {
+            log::info!("Cannot connect to the current instance: {:#}", e);
+            warn(
+                "Cannot connect to the current instance, \
+                 output shapes of queries are not compared.",
+            );
+        }
+    }
+    Ok(queries)
+}
+
+fn schema_error(err: &Error, source_map: &SourceMap<SourceName>) -> SchemaError {
+    let (file, line, column) = match error_location(err, source_map) {
+        Some(loc) => (Some(loc.path), Some(loc.line), Some(loc.column)),
+        None => (None, None, None),
+    };
+    SchemaError {
+        kind: err.kind_name().into(),
+        message: err.initial_message().unwrap_or(err.kind_name()).into(),
+        hint: err.hint().map(|h| h.into()),
+        file,
+        line,
+        column,
+    }
+}
+
+/// Applies the migration the same way `apply_migration` does, but records
+/// the error instead of failing
+async fn check_migration(
+    cli: &mut Connection,
+    migration: &MigrationFile,
+    json: bool,
+) -> anyhow::Result<MigrationReport> {
+    let data = fs::read_to_string(&migration.path)
+        .await
+        .context("error re-reading migration file")?;
+    let mut report = MigrationReport::new(migration, MigrationStatus::Applied);
+    let started = Instant::now();
+    if let Err(err) = cli.execute(&data, &()).await {
+        if !json {
+            let fname = migration.path.display().to_string();
+            print_query_error(&err, &data, false, &fname)?;
+        }
+        report.status = MigrationStatus::Failed;
+        report.error = Some(err.to_string());
+        if let Some(pos) = err.position_start() {
+            let (line, column) = line_column(&data, pos);
+            report.line = Some(line);
+            report.column = Some(column);
+        }
+    }
+    report.duration_ms = millis(started);
+    Ok(report)
+}
+
+impl MigrationReport {
+    fn new(migration: &MigrationFile, status: MigrationStatus) -> MigrationReport {
+        MigrationReport {
+            id: migration.data.id.clone(),
+            file: migration.path.clone(),
+            status,
+            error: None,
+            line: None,
+            column: None,
+            duration_ms: 0,
+        }
     }
 }
 
@@ -285,21 +857,25 @@ fn print_apply_migration_error() {
     echo!("    edgedb migration create --squash".command_hint());
 }
 
-pub async fn watch_loop(
+async fn watch_loop(
     mut rx: watch::Receiver<()>,
     ctx: &Context,
     cli: &mut Connection,
     mut ok: bool,
-) -> anyhow::Result<()> {
+    queries: &[QueryFile],
+) -> anyhow::Result<Infallible> {
     let mut retry_deadline = None::<Instant>;
     loop {
         // note we don't wait for interrupt here because if interrupt happens
         // the `background_for` method of the process takes care of it.
-        cli.ping_while(wait_changes(&mut rx, retry_deadline))
+        cli.ping_while(wait_changes(&mut rx, retry_deadline, STABLE_TIME))
             .await?;
         retry_deadline = None;
-        match single_check(ctx, cli).await {
-            Ok(CheckResult::Okay) => {
+        match single_check(ctx, cli, false, queries)
+            .await
+            .map(|r| r.result)
+        {
+            Ok(Some(CheckResult::Okay)) => {
                 if !ok {
                     success(
                         "The schema is forward compatible. \
@@ -323,3 +899,78 @@ pub async fn watch_loop(
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use edgedb_protocol::codec;
+    use edgedb_protocol::common::Cardinality;
+    use edgedb_protocol::descriptors::{BaseScalarTypeDescriptor, ScalarTypeDescriptor};
+    use edgedb_protocol::descriptors::{Descriptor, SetDescriptor, TypePos};
+    use edgedb_protocol::descriptors::{ObjectShapeDescriptor, ShapeElement};
+    use uuid::Uuid;
+
+    use super::{parse_matrix, shape_text};
+
+    fn element(name: &str, cardinality: Cardinality, type_pos: u16) -> ShapeElement {
+        ShapeElement {
+            flag_implicit: name == "id",
+            flag_link_property: false,
+            flag_link: false,
+            cardinality: Some(cardinality),
+            name: name.into(),
+            type_pos: TypePos(type_pos),
+        }
+    }
+
+    fn user_type_shape(type_id: u128) -> Vec<Descriptor> {
+        vec![
+            Descriptor::BaseScalar(BaseScalarTypeDescriptor {
+                id: codec::STD_STR.into(),
+            }),
+            Descriptor::BaseScalar(BaseScalarTypeDescriptor {
+                id: codec::STD_UUID.into(),
+            }),
+            Descriptor::Scalar(ScalarTypeDescriptor {
+                id: Uuid::from_u128(type_id + 1).into(),
+                base_type_pos: TypePos(0),
+            }),
+            Descriptor::ObjectShape(ObjectShapeDescriptor {
+                id: Uuid::from_u128(type_id).into(),
+                elements: vec![
+                    element("id", Cardinality::One, 1),
+                    element("name", Cardinality::One, 0),
+                    element("nick", Cardinality::AtMostOne, 2),
+                ],
+            }),
+            Descriptor::Set(SetDescriptor {
+                id: Uuid::from_u128(type_id + 2).into(),
+                type_pos: TypePos(3),
+            }),
+        ]
+    }
+
+    #[test]
+    fn matrix() {
+        let targets = parse_matrix("5, stable,nightly,").unwrap();
+        let names: Vec<_> = targets.iter().map(|q| q.display().to_string()).collect();
+        assert_eq!(names, ["5.0", "stable", "nightly"]);
+        assert!(parse_matrix(",").is_err());
+        assert!(parse_matrix("5,latest").is_err());
+    }
+
+    #[test]
+    fn shape() {
+        let shape = shape_text(&user_type_shape(0x1000), Some(TypePos(4)));
+        assert_eq!(shape, "set<{name: One std::str, nick: AtMostOne std::str}>");
+        // User-defined types get different ids on another instance
+        assert_eq!(
+            shape_text(&user_type_shape(0x2000), Some(TypePos(4))),
+            shape
+        );
+        assert_eq!(
+            shape_text(&user_type_shape(0x1000), Some(TypePos(1))),
+            "std::uuid"
+        );
+        assert_eq!(shape_text(&[], None), "nothing");
+    }
+}
@@ -77,6 +77,7 @@ mod test {
         let ctx = Context {
             schema_dir,
             edgedb_version: None,
+            migrations: Default::default(),
             quiet: false,
         };
 
@@ -29,6 +29,7 @@ use crate::portable::options::InstanceName;
 use crate::portable::project;
 use crate::print;
 use crate::repl::OutputFormat;
+use crate::schema::options::SchemaCommand;
 use crate::tty_password;
 use crate::watch::options::WatchCommand;
 
@@ -373,6 +374,8 @@ pub enum Command {
     Watch(WatchCommand),
     /// Manage branches
     Branch(BranchCommand),
+    /// Work with schema files
+    Schema(SchemaCommand),
 
     HashPassword(HashPasswordCommand),
 }
@@ -471,7 +474,7 @@ impl UsageError {
     }
 }
 
-fn parse_duration(value: &str) -> anyhow::Result<Duration> {
+pub(crate) fn parse_duration(value: &str) -> anyhow::Result<Duration> {
     let value = value.parse::<model::Duration>()?;
     match value.is_negative() {
         false => Ok(value.abs_duration()),
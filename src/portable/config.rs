@@ -2,6 +2,7 @@ use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use anyhow::Context as _;
 use fn_error_context::context;
 
 use toml::Spanned;
@@ -17,6 +18,8 @@ use crate::print::{self, echo, Highlight};
 pub struct SrcConfig {
     pub edgedb: SrcEdgedb,
     pub project: Option<SrcProject>,
+    pub migrations: Option<SrcMigrations>,
+    pub watch: Option<SrcWatch>,
     #[serde(flatten)]
     pub extra: BTreeMap<String, toml::Value>,
 }
@@ -39,10 +42,45 @@ pub struct SrcProject {
     pub extra: BTreeMap<String, toml::Value>,
 }
 
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SrcMigrations {
+    #[serde(default)]
+    pub header_template: Option<String>,
+    #[serde(default)]
+    pub lint: BTreeMap<String, LintLevel>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, toml::Value>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SrcWatch {
+    #[serde(default)]
+    pub on_success: Option<String>,
+    #[serde(default)]
+    pub on_failure: Option<String>,
+    #[serde(default)]
+    pub files: Vec<SrcWatchFiles>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, toml::Value>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SrcWatchFiles {
+    pub glob: String,
+    pub run: String,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, toml::Value>,
+}
+
 #[derive(Debug)]
 pub struct Config {
     pub edgedb: Edgedb,
     pub project: Project,
+    pub migrations: Migrations,
+    pub watch: Watch,
 }
 
 #[derive(Debug)]
@@ -55,6 +93,42 @@ pub struct Project {
     pub schema_dir: PathBuf,
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct Migrations {
+    /// Comment lines written on top of newly created migration files.
+    /// Supports `{author}`, `{timestamp}` and `{ticket}` placeholders.
+    pub header_template: Option<String>,
+    /// Levels of `migration lint` rules, keyed by rule name.
+    pub lint: BTreeMap<String, LintLevel>,
+}
+
+/// Hooks run by `edgedb watch`. Commands are run by the shell in
+/// the project directory.
+#[derive(Debug, Clone, Default)]
+pub struct Watch {
+    /// Command run after schema changes are applied.
+    pub on_success: Option<String>,
+    /// Command run when schema changes fail to apply.
+    pub on_failure: Option<String>,
+    /// Commands run when files matching a glob are changed.
+    pub files: Vec<WatchFiles>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WatchFiles {
+    /// Glob relative to the project directory.
+    pub glob: glob::Pattern,
+    pub run: String,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Error,
+}
+
 pub fn warn_extra(extra: &BTreeMap<String, toml::Value>, prefix: &str) {
     for key in extra.keys() {
         log::warn!("Unknown config option `{}{}`", prefix, key.escape_default());
@@ -78,6 +152,23 @@ pub fn read(path: &Path) -> anyhow::Result<Config> {
     let val: SrcConfig = serde_path_to_error::deserialize(&mut toml)?;
     warn_extra(&val.extra, "");
     warn_extra(&val.edgedb.extra, "edgedb.");
+    if let Some(migrations) = &val.migrations {
+        warn_extra(&migrations.extra, "migrations.");
+    }
+    let mut watch = Watch::default();
+    if let Some(src) = val.watch {
+        warn_extra(&src.extra, "watch.");
+        for files in src.files {
+            warn_extra(&files.extra, "watch.files.");
+            watch.files.push(WatchFiles {
+                glob: glob::Pattern::new(&files.glob)
+                    .with_context(|| format!("invalid glob {:?} in `watch.files`", files.glob))?,
+                run: files.run,
+            });
+        }
+        watch.on_success = src.on_success;
+        watch.on_failure = src.on_failure;
+    }
 
     return Ok(Config {
         edgedb: Edgedb {
@@ -97,6 +188,14 @@ pub fn read(path: &Path) -> anyhow::Result<Config> {
                 .map(|s| s.into())
                 .unwrap_or_else(|| path.parent().unwrap_or(Path::new("")).join("dbschema")),
         },
+        migrations: val
+            .migrations
+            .map(|m| Migrations {
+                header_template: m.header_template,
+                lint: m.lint,
+            })
+            .unwrap_or_default(),
+        watch,
     });
 }
 
@@ -323,4 +422,34 @@ mod test {
     fn modify(src: &str, ver: &str) -> Option<String> {
         set_toml_version(src, &ver.parse().unwrap()).unwrap()
     }
+
+    #[test]
+    fn watch_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("edgedb.toml");
+        std::fs::write(
+            &path,
+            "\
+            [edgedb]\n\
+            server-version = \"5\"\n\
+            [watch]\n\
+            on-success = \"npx @edgedb/generate queries\"\n\
+            [[watch.files]]\n\
+            glob = \"src/**/*.edgeql\"\n\
+            run = \"make queries\"\n\
+            ",
+        )
+        .unwrap();
+        let config = super::read(&path).unwrap();
+        assert_eq!(
+            config.watch.on_success.as_deref(),
+            Some("npx @edgedb/generate queries")
+        );
+        assert_eq!(config.watch.on_failure, None);
+        assert_eq!(config.watch.files.len(), 1);
+        assert!(config.watch.files[0]
+            .glob
+            .matches("src/queries/user.edgeql"));
+        assert_eq!(config.watch.files[0].run, "make queries");
+    }
 }
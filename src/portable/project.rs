@@ -1290,6 +1290,8 @@ async fn migrate_async(inst: &Handle<'_>, ask_for_running: bool) -> anyhow::Resu
             to_revision: None,
             dev_mode: false,
             single_transaction: false,
+            dry_run: false,
+            rollback: false,
             conn: None,
         },
     )
@@ -459,7 +459,7 @@ fn show_history(history: &History) -> Result<(), anyhow::Error> {
     }
 }
 
-fn spawn_editor(data: &str) -> Result<String, anyhow::Error> {
+pub fn spawn_editor(data: &str) -> Result<String, anyhow::Error> {
     let mut temp_file = tempfile::Builder::new().suffix(".edgeql").tempfile()?;
     temp_file.write_all(data.as_bytes())?;
     let temp_path = temp_file.into_temp_path();
@@ -127,7 +127,6 @@ impl<'a> String<'a> {
 }
 
 impl String<'static> {
-    #[allow(dead_code)]
     pub async fn async_ask(mut self) -> anyhow::Result<std::string::String> {
         spawn_blocking(move || self.ask()).await?
     }
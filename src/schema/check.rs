@@ -0,0 +1,251 @@
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use codespan_reporting::files::SimpleFile;
+use codespan_reporting::term::emit;
+use edgeql_parser::schema_file::validate;
+use edgeql_parser::tokenizer::{Kind, Tokenizer};
+use termcolor::{ColorChoice, StandardStream};
+use tokio::fs;
+
+use crate::commands::{ExitCode, Options};
+use crate::migrations::source_map::Builder;
+use crate::migrations::Context;
+use crate::print;
+use crate::schema::options::Check;
+
+struct Error {
+    path: PathBuf,
+    message: String,
+    range: Range<usize>,
+    label: &'static str,
+}
+
+pub async fn main(_options: &Options, params: &Check) -> anyhow::Result<()> {
+    let ctx = Context::from_project_or_config(&params.cfg, false).await?;
+    let paths = schema_files(&ctx.schema_dir).await?;
+    let mut files = Vec::with_capacity(paths.len());
+    for path in paths {
+        let text = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("cannot read {:?}", path))?;
+        files.push((path, text));
+    }
+
+    let errors = check(&files);
+    for error in &errors {
+        let text = files
+            .iter()
+            .find(|(path, _)| path == &error.path)
+            .map(|(_, text)| &text[..])
+            .unwrap_or_default();
+        emit_error(error, text)?;
+    }
+    if !errors.is_empty() {
+        print::error(format!(
+            "Found {} error(s) in schema files in {:?}.",
+            errors.len(),
+            ctx.schema_dir,
+        ));
+        return Err(ExitCode::new(1))?;
+    }
+    print::success(format!(
+        "Checked {} schema files, no errors found.",
+        files.len()
+    ));
+    Ok(())
+}
+
+/// Returns `.esdl` and `.gel` files at the top level of the schema
+/// directory, sorted by name. These are the files sent to the server by
+/// migration commands.
+pub async fn schema_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => Err(e).context(format!("cannot read {:?}", dir))?,
+    };
+    let mut paths = Vec::new();
+    while let Some(item) = entries.next_entry().await? {
+        let fname = item.file_name();
+        let lossy_name = fname.to_string_lossy();
+        if !lossy_name.starts_with('.')
+            && (lossy_name.ends_with(".esdl") || lossy_name.ends_with(".gel"))
+            && item.file_type().await?.is_file()
+        {
+            paths.push(item.path());
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn check(files: &[(PathBuf, String)]) -> Vec<Error> {
+    // Files are tokenized together, the same way they are sent to
+    // the server, so that brackets left open in one file are reported
+    let mut bld = Builder::new();
+    for (path, text) in files {
+        bld.add_lines(Some(path.clone()), text);
+        bld.add_lines(None, ";");
+    }
+    let (buffer, source_map) = bld.done();
+
+    let mut errors = Vec::new();
+    let mut report = |start: usize, end: usize, message: String, label| {
+        if let Ok((Some(path), offset)) = source_map.translate_range(start, end) {
+            errors.push(Error {
+                path: path.clone(),
+                message,
+                range: start - offset..end - offset,
+                label,
+            });
+        }
+    };
+    let mut open = Vec::new();
+    for token in Tokenizer::new(&buffer) {
+        let token = match token {
+            Ok(token) => token,
+            Err(e) => {
+                let (start, end) = (e.span.start as usize, e.span.end as usize);
+                report(start, end, e.message, "invalid token");
+                // The tokenizer can't recover, so brackets are not checked
+                open.clear();
+                break;
+            }
+        };
+        let (start, end) = (token.span.start as usize, token.span.end as usize);
+        match token.kind {
+            Kind::OpenBrace | Kind::OpenParen | Kind::OpenBracket => {
+                open.push((token.kind, start, end));
+            }
+            Kind::CloseBrace | Kind::CloseParen | Kind::CloseBracket => {
+                let expected = match token.kind {
+                    Kind::CloseBrace => Kind::OpenBrace,
+                    Kind::CloseParen => Kind::OpenParen,
+                    _ => Kind::OpenBracket,
+                };
+                match open.pop() {
+                    Some((kind, _, _)) if kind == expected => {}
+                    Some((_, start, end)) => {
+                        report(
+                            start,
+                            end,
+                            format!("Bracket is closed by a mismatched {:?}", token.text),
+                            "unclosed bracket",
+                        );
+                    }
+                    None => {
+                        report(
+                            start,
+                            end,
+                            format!("Unexpected closing bracket {:?}", token.text),
+                            "no matching opening bracket",
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for (_, start, end) in open {
+        report(
+            start,
+            end,
+            "Bracket is never closed".into(),
+            "unclosed bracket",
+        );
+    }
+
+    // The structure of files is only checked if they tokenize cleanly
+    for (path, text) in files {
+        if errors.iter().any(|e| &e.path == path) {
+            continue;
+        }
+        if let Err(e) = validate(text) {
+            errors.push(Error {
+                path: path.clone(),
+                message: e.message,
+                range: e.span.start as usize..e.span.end as usize,
+                label: "unexpected token",
+            });
+        }
+    }
+    errors
+}
+
+fn emit_error(error: &Error, text: &str) -> anyhow::Result<()> {
+    let file_name = error.path.display();
+    let files = SimpleFile::new(&file_name, text);
+    let diag = Diagnostic::error()
+        .with_message(&error.message)
+        .with_labels(vec![
+            Label::primary((), error.range.clone()).with_message(error.label)
+        ]);
+    emit(
+        &mut StandardStream::stderr(ColorChoice::Auto),
+        &Default::default(),
+        &files,
+        &diag,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::check;
+
+    fn errors(files: &[(&str, &str)]) -> Vec<(String, String)> {
+        let files: Vec<_> = files
+            .iter()
+            .map(|(name, text)| (PathBuf::from(name), text.to_string()))
+            .collect();
+        check(&files)
+            .into_iter()
+            .map(|e| {
+                let (_, text) = files.iter().find(|(path, _)| path == &e.path).unwrap();
+                (e.path.display().to_string(), text[e.range].to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid() {
+        let text = "module default { type User { property name -> str; } }";
+        assert!(errors(&[("default.esdl", text)]).is_empty());
+    }
+
+    #[test]
+    fn unclosed() {
+        assert_eq!(
+            errors(&[
+                ("a.esdl", "module default { type A {"),
+                ("b.esdl", "module other {}")
+            ]),
+            vec![("a.esdl".into(), "{".into()), ("a.esdl".into(), "{".into()),],
+        );
+    }
+
+    #[test]
+    fn unexpected_close() {
+        assert_eq!(
+            errors(&[("a.esdl", "module default { } }")]),
+            vec![("a.esdl".into(), "}".into())],
+        );
+    }
+
+    #[test]
+    fn invalid_structure() {
+        assert_eq!(
+            errors(&[
+                ("a.esdl", "module default { type A; }"),
+                ("b.esdl", "module other { type B; }\n42"),
+            ]),
+            vec![("b.esdl".into(), "42".into())],
+        );
+    }
+}
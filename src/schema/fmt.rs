@@ -0,0 +1,448 @@
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use edgeql_parser::tokenizer::{Kind, Token, Tokenizer};
+use tokio::fs;
+
+use crate::commands::{ExitCode, Options};
+use crate::migrations::Context;
+use crate::print;
+use crate::schema::check::schema_files;
+use crate::schema::options::Fmt;
+
+const INDENT: &str = "    ";
+
+/// A declaration, possibly with a block of nested declarations
+#[derive(Debug, Default)]
+struct Item {
+    comments: Vec<String>,
+    blank_before: bool,
+    header: String,
+    block: Option<Block>,
+    semicolon: bool,
+    trailing_comment: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Block {
+    items: Vec<Item>,
+    /// Comments after the last declaration in the block
+    comments: Vec<String>,
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    prev_end: usize,
+}
+
+pub async fn main(_options: &Options, params: &Fmt) -> anyhow::Result<()> {
+    let ctx = Context::from_project_or_config(&params.cfg, false).await?;
+    let mut changed: Vec<PathBuf> = Vec::new();
+    for path in schema_files(&ctx.schema_dir).await? {
+        let text = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("cannot read {:?}", path))?;
+        let formatted = format_schema(&text).with_context(|| {
+            format!(
+                "cannot format {:?}, run `edgedb schema check` for details",
+                path
+            )
+        })?;
+        if formatted != text {
+            if params.check {
+                eprintln!("Would reformat {}", path.display());
+            } else {
+                fs::write(&path, formatted)
+                    .await
+                    .with_context(|| format!("cannot write {:?}", path))?;
+            }
+            changed.push(path);
+        }
+    }
+    if params.check {
+        if !changed.is_empty() {
+            print::error(format!("{} schema files need formatting.", changed.len()));
+            return Err(ExitCode::new(1))?;
+        }
+        print::success("All schema files are formatted.");
+    } else {
+        print::success(format!("Reformatted {} schema files.", changed.len()));
+    }
+    Ok(())
+}
+
+/// Formats SDL text. Comments are preserved, annotations are moved to
+/// the top of their block.
+pub fn format_schema(text: &str) -> anyhow::Result<String> {
+    let mut out = String::with_capacity(text.len());
+    write_block(&mut out, parse(text)?, 0);
+    Ok(out)
+}
+
+/// Splits SDL into formatted text per top-level module. Declarations
+/// outside of modules (such as `using extension`) are put at the top of
+/// the `default` module file.
+pub fn split_modules(text: &str) -> anyhow::Result<Vec<(String, String)>> {
+    let mut toplevel = Block::default();
+    let mut modules = Vec::new();
+    for item in parse(text)?.items {
+        match item.header.strip_prefix("module ") {
+            Some(name) if item.block.is_some() => {
+                let name = name.trim().trim_matches('`').to_string();
+                let block = Block {
+                    items: vec![item],
+                    comments: Vec::new(),
+                };
+                modules.push((name, block));
+            }
+            _ => toplevel.items.push(item),
+        }
+    }
+    if !toplevel.items.is_empty() {
+        match modules.iter_mut().find(|(name, _)| name == "default") {
+            Some((_, block)) => {
+                toplevel.items.append(&mut block.items);
+                *block = toplevel;
+            }
+            None => modules.insert(0, ("default".into(), toplevel)),
+        }
+    }
+    Ok(modules
+        .into_iter()
+        .map(|(name, block)| {
+            let mut out = String::new();
+            write_block(&mut out, block, 0);
+            (name, out)
+        })
+        .collect())
+}
+
+fn parse(text: &str) -> anyhow::Result<Block> {
+    let tokens = Tokenizer::new(text)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("{}", e.message))?;
+    let mut parser = Parser {
+        text,
+        tokens,
+        pos: 0,
+        prev_end: 0,
+    };
+    let block = parser.block()?;
+    if parser.pos < parser.tokens.len() {
+        anyhow::bail!("unexpected closing brace");
+    }
+    Ok(block)
+}
+
+fn comment_lines(gap: &str) -> impl Iterator<Item = String> + '_ {
+    gap.lines()
+        .filter_map(|line| line.find('#').map(|idx| line[idx..].trim_end().to_string()))
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Text between the previous token and the current one
+    fn gap(&self) -> &'a str {
+        let end = self
+            .peek()
+            .map(|t| t.span.start as usize)
+            .unwrap_or(self.text.len());
+        &self.text[self.prev_end..end]
+    }
+
+    fn advance(&mut self) -> &Token<'a> {
+        let token = &self.tokens[self.pos];
+        self.prev_end = token.span.end as usize;
+        self.pos += 1;
+        token
+    }
+
+    /// Parses declarations until a closing brace or end of input
+    fn block(&mut self) -> anyhow::Result<Block> {
+        let mut block = Block::default();
+        loop {
+            let gap = self.gap();
+            // A comment on the same line as the previous declaration
+            let (first_line, rest) = gap.split_once('\n').unwrap_or((gap, ""));
+            let mut lines = gap.split('\n');
+            lines.next();
+            lines.next_back();
+            let blank_before = lines.any(|l| l.trim().is_empty());
+            let mut comments: Vec<String> = Vec::new();
+            match block.items.last_mut() {
+                Some(last) if first_line.contains('#') => {
+                    last.trailing_comment = comment_lines(first_line).next();
+                    comments.extend(comment_lines(rest));
+                }
+                _ => comments.extend(comment_lines(gap)),
+            }
+
+            match self.peek() {
+                None => {
+                    block.comments = comments;
+                    return Ok(block);
+                }
+                Some(t) if t.kind == Kind::CloseBrace => {
+                    block.comments = comments;
+                    return Ok(block);
+                }
+                Some(_) => {}
+            }
+            let mut item = self.item()?;
+            item.comments = comments;
+            item.blank_before = blank_before;
+            block.items.push(item);
+        }
+    }
+
+    fn item(&mut self) -> anyhow::Result<Item> {
+        let mut item = Item::default();
+        let mut depth = 0;
+        let mut assign = false;
+        while let Some(token) = self.peek() {
+            let kind = token.kind;
+            if depth == 0 {
+                match kind {
+                    Kind::Semicolon => {
+                        self.advance();
+                        item.semicolon = true;
+                        return Ok(item);
+                    }
+                    Kind::CloseBrace => return Ok(item),
+                    Kind::OpenBrace if !assign => {
+                        self.advance();
+                        item.block = Some(self.block()?);
+                        match self.peek() {
+                            Some(t) if t.kind == Kind::CloseBrace => {
+                                self.advance();
+                            }
+                            _ => anyhow::bail!("unclosed brace in {:?}", item.header),
+                        }
+                        if matches!(self.peek(), Some(t) if t.kind == Kind::Semicolon) {
+                            self.advance();
+                            item.semicolon = true;
+                        }
+                        return Ok(item);
+                    }
+                    Kind::Assign => assign = true,
+                    _ => {}
+                }
+            }
+            match kind {
+                Kind::OpenBrace | Kind::OpenParen | Kind::OpenBracket => depth += 1,
+                Kind::CloseParen | Kind::CloseBracket | Kind::CloseBrace => depth -= 1,
+                _ => {}
+            }
+            if !item.header.is_empty() {
+                let gap = self.gap();
+                if gap.contains('#') {
+                    item.header.push_str(gap);
+                } else if !gap.is_empty() {
+                    item.header.push(' ');
+                }
+            }
+            let text = self.advance().text.to_string();
+            item.header.push_str(&text);
+        }
+        Ok(item)
+    }
+}
+
+fn is_annotation(item: &Item) -> bool {
+    item.header
+        .get(..11)
+        .map_or(false, |s| s.eq_ignore_ascii_case("annotation "))
+}
+
+fn write_block(out: &mut String, mut block: Block, depth: usize) {
+    if depth > 0 {
+        let (mut annotations, rest): (Vec<_>, Vec<_>) =
+            block.items.into_iter().partition(is_annotation);
+        for item in &mut annotations {
+            item.blank_before = false;
+        }
+        annotations.extend(rest);
+        block.items = annotations;
+    }
+    let indent = INDENT.repeat(depth);
+    let mut prev_block = false;
+    for (n, item) in block.items.into_iter().enumerate() {
+        // Module-level declarations with blocks are always separated
+        if n > 0 && (item.blank_before || (depth <= 1 && (prev_block || item.block.is_some()))) {
+            out.push('\n');
+        }
+        prev_block = item.block.is_some();
+        for comment in &item.comments {
+            out.push_str(&indent);
+            out.push_str(comment);
+            out.push('\n');
+        }
+        out.push_str(&indent);
+        out.push_str(&item.header);
+        if let Some(block) = item.block {
+            if block.items.is_empty() && block.comments.is_empty() {
+                out.push_str(" {}");
+            } else {
+                out.push_str(" {\n");
+                write_block(out, block, depth + 1);
+                out.push_str(&indent);
+                out.push('}');
+            }
+        }
+        if item.semicolon {
+            out.push(';');
+        }
+        if let Some(comment) = &item.trailing_comment {
+            out.push(' ');
+            out.push_str(comment);
+        }
+        out.push('\n');
+    }
+    let indent = INDENT.repeat(depth);
+    for comment in &block.comments {
+        out.push_str(&indent);
+        out.push_str(comment);
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_schema, split_modules};
+
+    fn fmt(text: &str) -> String {
+        let res = format_schema(text).unwrap();
+        assert_eq!(format_schema(&res).unwrap(), res, "not idempotent");
+        res
+    }
+
+    #[test]
+    fn indentation() {
+        assert_eq!(
+            fmt(
+                "module default{\ntype User{\n  required property name -> str;\n\
+                 multi link friends -> User {constraint exclusive;}}}"
+            ),
+            "\
+module default {
+    type User {
+        required property name -> str;
+        multi link friends -> User {
+            constraint exclusive;
+        }
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn blank_lines() {
+        assert_eq!(
+            fmt(
+                "module default {\n  type A;\n  type B {\n\n\n  property x -> str;\n\n\n\
+                 property y -> str;\n  }\n  scalar type S extending str;\n}\n"
+            ),
+            "\
+module default {
+    type A;
+
+    type B {
+        property x -> str;
+
+        property y -> str;
+    }
+
+    scalar type S extending str;
+}
+"
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            fmt("# users\nmodule default {\n  # the user\n  type User {\n\
+                 property name -> str;  # display name\n  # more later\n  }\n}\n"),
+            "\
+# users
+module default {
+    # the user
+    type User {
+        property name -> str; # display name
+        # more later
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn annotations_first() {
+        assert_eq!(
+            fmt("module default { type User { property name -> str; \
+                 annotation title := 'User'; } }"),
+            "\
+module default {
+    type User {
+        annotation title := 'User';
+        property name -> str;
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn expressions() {
+        assert_eq!(
+            fmt("module default {\n    alias Users := User { name };\n    \
+                 function f(x: int64) -> int64 using (x + 1);\n}\n"),
+            "\
+module default {
+    alias Users := User { name };
+    function f(x: int64) -> int64 using (x + 1);
+}
+"
+        );
+    }
+
+    #[test]
+    fn modules() {
+        let modules = split_modules(
+            "using extension pgvector version '0.5';\n\
+             module default { type A; };\nmodule other { type B; };",
+        )
+        .unwrap();
+        assert_eq!(
+            modules,
+            vec![
+                (
+                    "default".into(),
+                    "\
+using extension pgvector version '0.5';
+
+module default {
+    type A;
+};
+"
+                    .into()
+                ),
+                (
+                    "other".into(),
+                    "\
+module other {
+    type B;
+};
+"
+                    .into()
+                ),
+            ]
+        );
+    }
+}
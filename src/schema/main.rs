@@ -0,0 +1,15 @@
+use crate::commands::Options;
+use crate::schema::options::{Command, SchemaCommand};
+use crate::schema::{check, fmt, pull};
+
+#[tokio::main(flavor = "current_thread")]
+pub async fn schema_main(options: &Options, cmd: &SchemaCommand) -> anyhow::Result<()> {
+    match &cmd.subcommand {
+        Command::Check(params) => check::main(options, params).await,
+        Command::Fmt(params) => fmt::main(options, params).await,
+        Command::Pull(params) => {
+            let mut cli = options.conn_params.connect().await?;
+            pull::main(&mut cli, options, params).await
+        }
+    }
+}
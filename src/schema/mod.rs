@@ -0,0 +1,8 @@
+pub mod options;
+
+mod check;
+mod fmt;
+mod main;
+mod pull;
+
+pub use main::schema_main;
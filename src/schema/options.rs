@@ -0,0 +1,56 @@
+use crate::migrations::options::MigrationConfig;
+use crate::options::ConnectionOptions;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct SchemaCommand {
+    #[command(flatten)]
+    pub conn: ConnectionOptions,
+
+    #[command(subcommand)]
+    pub subcommand: Command,
+}
+
+#[derive(clap::Subcommand, Clone, Debug)]
+pub enum Command {
+    /// Check schema files for syntax errors without connecting to
+    /// the server.
+    Check(Check),
+    /// Reformat schema files: indentation, braces, blank lines between
+    /// declarations and order of annotations. Comments are kept.
+    Fmt(Fmt),
+    /// Write the schema of the database into the schema directory as
+    /// one `.esdl` file per module. Useful for databases whose schema was
+    /// changed using DDL commands.
+    Pull(Pull),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct Check {
+    #[command(flatten)]
+    pub cfg: MigrationConfig,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct Fmt {
+    #[command(flatten)]
+    pub cfg: MigrationConfig,
+
+    /// Do not write files, exit with non-zero status if any of them
+    /// need to be reformatted.
+    #[arg(long)]
+    pub check: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct Pull {
+    #[command(flatten)]
+    pub cfg: MigrationConfig,
+
+    /// Don't ask questions, abort if existing files would be overwritten.
+    #[arg(long)]
+    pub non_interactive: bool,
+
+    /// Overwrite existing schema files without asking.
+    #[arg(long)]
+    pub force: bool,
+}
@@ -0,0 +1,75 @@
+use anyhow::Context as _;
+use tokio::fs;
+
+use crate::commands::{ExitCode, Options};
+use crate::connect::Connection;
+use crate::migrations::Context;
+use crate::portable::exit_codes;
+use crate::print;
+use crate::question;
+use crate::schema::check::schema_files;
+use crate::schema::fmt::split_modules;
+use crate::schema::options::Pull;
+
+pub async fn main(cli: &mut Connection, _options: &Options, params: &Pull) -> anyhow::Result<()> {
+    let ctx = Context::from_project_or_config(&params.cfg, params.non_interactive).await?;
+    let sdl: String = cli
+        .query_required_single("DESCRIBE SCHEMA AS SDL", &())
+        .await?;
+    let modules = split_modules(&sdl).context("cannot parse schema returned by the server")?;
+    if modules.is_empty() {
+        print::warn("The database schema is empty. No files were written.");
+        return Ok(());
+    }
+
+    let new_paths: Vec<_> = modules
+        .iter()
+        .map(|(name, _)| {
+            ctx.schema_dir
+                .join(format!("{}.esdl", name.replace("::", ".")))
+        })
+        .collect();
+    let existing = schema_files(&ctx.schema_dir).await?;
+    let overwritten = existing.iter().filter(|p| new_paths.contains(p)).count();
+    if overwritten > 0 && !params.force {
+        if params.non_interactive {
+            anyhow::bail!(
+                "{} schema files in {:?} would be overwritten, \
+                 use `--force` to overwrite them",
+                overwritten,
+                ctx.schema_dir,
+            );
+        }
+        let q = question::Confirm::new_dangerous(format!(
+            "Overwrite {} existing schema files in {:?}?",
+            overwritten, ctx.schema_dir,
+        ));
+        if !q.async_ask().await? {
+            print::error("Canceled.");
+            return Err(ExitCode::new(exit_codes::NOT_CONFIRMED))?;
+        }
+    }
+
+    fs::create_dir_all(&ctx.schema_dir)
+        .await
+        .with_context(|| format!("cannot create {:?}", ctx.schema_dir))?;
+    for (path, (_, text)) in new_paths.iter().zip(&modules) {
+        fs::write(path, text)
+            .await
+            .with_context(|| format!("cannot write {:?}", path))?;
+        eprintln!("Wrote {}", path.display());
+    }
+    for path in existing.iter().filter(|p| !new_paths.contains(p)) {
+        print::warn(format!(
+            "{} is not produced from the database schema, \
+             remove it if it declares the same modules.",
+            path.display(),
+        ));
+    }
+    print::success(format!(
+        "Database schema written to {} files in {:?}.",
+        modules.len(),
+        ctx.schema_dir
+    ));
+    Ok(())
+}
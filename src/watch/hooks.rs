@@ -0,0 +1,57 @@
+use std::path::Path;
+use std::process::Stdio;
+
+use anyhow::Context;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+
+use crate::print;
+
+/// Runs a hook command with the shell in the project directory. Output
+/// of the command is prefixed by the hook name.
+pub async fn run(project_dir: &Path, name: &str, command: &str) {
+    if let Err(e) = _run(project_dir, name, command).await {
+        print::error(format!("Hook `{name}` failed to run: {e:#}"));
+    }
+}
+
+async fn _run(project_dir: &Path, name: &str, command: &str) -> anyhow::Result<()> {
+    log::info!("Running hook {name}: {command:?}");
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    cmd.arg(command);
+    cmd.current_dir(project_dir);
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    cmd.kill_on_drop(true);
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("cannot run {command:?}"))?;
+    let out = child.stdout.take();
+    let err = child.stderr.take();
+    let (status, (), ()) =
+        tokio::join!(child.wait(), print_lines(name, out), print_lines(name, err));
+    let status = status.with_context(|| format!("cannot wait for {command:?}"))?;
+    if status.success() {
+        eprintln!("Hook `{name}` finished successfully.");
+    } else {
+        print::warn(format!("Hook `{name}` failed: {status}"));
+    }
+    Ok(())
+}
+
+async fn print_lines(name: &str, pipe: Option<impl AsyncRead + Unpin>) {
+    let Some(pipe) = pipe else { return };
+    let mut lines = BufReader::new(pipe).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        eprintln!("[{name}] {line}");
+    }
+}
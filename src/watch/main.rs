@@ -1,5 +1,12 @@
+use std::env;
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+use anyhow::Context as _;
 use edgedb_tokio::{get_project_dir, Error};
 use edgeql_parser::helpers::quote_string;
 use indicatif::ProgressBar;
@@ -8,17 +15,71 @@ use tokio::sync::watch;
 use tokio::time::timeout;
 
 use crate::connect::{Connection, Connector};
+use crate::error_display::print_query_error;
 use crate::interrupt::Interrupt;
-use crate::migrations::{self, dev_mode};
+use crate::migrations::options::MigrationConfig;
+use crate::migrations::EsdlError;
+use crate::migrations::{self, dev_mode, line_column};
 use crate::options::Options;
+use crate::portable::config;
+use crate::print;
+use crate::watch::hooks;
 use crate::watch::options::WatchCommand;
+use crate::watch::queries::{Outcome, Queries};
 
-const STABLE_TIME: Duration = Duration::from_millis(100);
+pub const STABLE_TIME: Duration = Duration::from_millis(100);
+
+/// Editor swap and backup files
+const DEFAULT_IGNORE: &[&str] = &["*.swp", "*.swx", "*~", "4913", ".#*", "#*#"];
 
 struct WatchContext {
     connector: Connector,
     migration: migrations::Context,
+    /// Project directory, or the current directory outside of projects
+    project_dir: PathBuf,
+    debounce: Duration,
+    hooks: config::Watch,
+    queries: Option<Queries>,
+    /// Paths changed since the last update
+    changes: Arc<Mutex<Vec<PathBuf>>>,
+    /// Set while a hook runs, hooks usually write generated files which
+    /// may be watched too
+    hook_running: Arc<AtomicBool>,
     last_error: bool,
+    json: bool,
+}
+
+/// Event printed by `--json`
+#[derive(serde::Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    Started {
+        project_dir: Option<&'a Path>,
+        schema_dir: &'a Path,
+    },
+    ChangeDetected {
+        paths: &'a [PathBuf],
+    },
+    Applied {
+        ddl: Vec<String>,
+    },
+    Error {
+        error: &'a ErrorJson,
+    },
+    Cleared,
+    QueryError {
+        file: &'a Path,
+        error: &'a ErrorJson,
+    },
+    CardinalityChanged {
+        file: &'a Path,
+        old: &'a str,
+        new: &'a str,
+    },
+    QueriesChecked {
+        total: usize,
+        failed: usize,
+    },
 }
 
 #[derive(serde::Serialize)]
@@ -43,43 +104,106 @@ struct ErrorJson {
     context: Option<ErrorContext>,
 }
 
-pub fn watch(options: &Options, _watch: &WatchCommand) -> anyhow::Result<()> {
+pub fn watch(options: &Options, cmd: &WatchCommand) -> anyhow::Result<()> {
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .thread_name("watch")
         .enable_all()
         .build()?;
-    let project_dir = match runtime.block_on(get_project_dir(None, true))? {
-        Some(proj) => proj,
-        None => anyhow::bail!(
-            "The `edgedb watch` command currently only \
-             works for projects. Run `edgedb project init` first."
-        ),
+    let project = runtime.block_on(get_project_dir(None, true))?;
+    let (project_dir, mut migration, hooks) = match &project {
+        Some(project_dir) => {
+            let config = config::read(&project_dir.join("edgedb.toml"))?;
+            let mut migration = migrations::Context::for_project(&config)?;
+            if let Some(schema_dir) = &cmd.schema_dir {
+                migration.schema_dir = schema_dir.clone();
+            }
+            (project_dir.clone(), migration, config.watch)
+        }
+        None => {
+            let cfg = MigrationConfig {
+                schema_dir: cmd.schema_dir.clone(),
+            };
+            let migration =
+                runtime.block_on(migrations::Context::from_project_or_config(&cfg, false))?;
+            (env::current_dir()?, migration, config::Watch::default())
+        }
     };
+    let ignore = DEFAULT_IGNORE
+        .iter()
+        .copied()
+        .chain(cmd.ignore.iter().map(|s| &s[..]))
+        .map(|glob| glob::Pattern::new(glob).with_context(|| format!("invalid glob {:?}", glob)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    // Paths of filesystem events are compared to these directories, so
+    // they must be in the same form as the watched paths
+    let project_dir = fs::canonicalize(&project_dir)
+        .with_context(|| format!("cannot resolve {:?}", project_dir))?;
+    migration.schema_dir = fs::canonicalize(&migration.schema_dir)
+        .with_context(|| format!("cannot find schema dir {:?}", migration.schema_dir))?;
+    let queries = cmd
+        .queries
+        .as_deref()
+        .map(|glob| Queries::new(glob, &migration.schema_dir))
+        .transpose()?;
     let mut ctx = WatchContext {
         connector: options.block_on_create_connector()?,
-        migration: migrations::Context::for_watch(&project_dir)?,
+        migration,
+        project_dir: project_dir.clone(),
+        debounce: cmd.debounce,
+        hooks,
+        queries,
+        changes: Arc::new(Mutex::new(Vec::new())),
+        hook_running: Arc::new(AtomicBool::new(false)),
         last_error: false,
+        json: cmd.json,
     };
-    log::info!("Initialized in project dir {:?}", project_dir);
+    log::info!("Initialized in dir {:?}", project_dir);
     let (tx, rx) = watch::channel(());
-    let mut watch = notify::recommended_watcher(move |res: Result<_, _>| {
-        res.map_err(|e| {
-            log::warn!("Error watching filesystem: {:#}", e);
-        })
-        .ok();
-        tx.send(()).unwrap();
+    let changes = ctx.changes.clone();
+    let hook_running = ctx.hook_running.clone();
+    let base_dir = project_dir.clone();
+    let mut watch = notify::recommended_watcher(move |res: Result<notify::Event, _>| {
+        match res {
+            // Changes made by hooks would trigger the hooks again
+            Ok(_) if hook_running.load(Ordering::SeqCst) => {}
+            Ok(event) => {
+                if let Some(paths) = changed_paths(&base_dir, &ignore, event) {
+                    changes.lock().unwrap().extend(paths);
+                    tx.send(()).unwrap();
+                }
+            }
+            Err(e) => log::warn!("Error watching filesystem: {:#}", e),
+        }
     })?;
-    watch.watch(
-        &project_dir.join("edgedb.toml"),
-        RecursiveMode::NonRecursive,
-    )?;
+    if project.is_some() {
+        watch.watch(
+            &project_dir.join("edgedb.toml"),
+            RecursiveMode::NonRecursive,
+        )?;
+    }
     watch.watch(&ctx.migration.schema_dir, RecursiveMode::Recursive)?;
+    let globs = ctx
+        .hooks
+        .files
+        .iter()
+        .map(|files| &files.glob)
+        .chain(ctx.queries.as_ref().map(|q| &q.glob));
+    for glob in globs {
+        let dir = glob_base(&project_dir, glob);
+        if let Err(e) = watch.watch(&dir, RecursiveMode::Recursive) {
+            log::warn!("Cannot watch {:?} for {:?}: {:#}", dir, glob.as_str(), e);
+        }
+    }
 
+    ctx.emit(Event::Started {
+        project_dir: project.as_deref(),
+        schema_dir: &ctx.migration.schema_dir,
+    });
     runtime.block_on(ctx.do_update())?;
 
     eprintln!("EdgeDB Watch initialized.");
     eprintln!("  Hint: Use `edgedb migration create` and `edgedb migrate --dev-mode` to apply changes once done.");
-    eprintln!("Monitoring {:?}.", project_dir);
+    eprintln!("Monitoring {:?}.", ctx.migration.schema_dir);
     let res = runtime.block_on(watch_loop(rx, &mut ctx));
     runtime
         .block_on(ctx.try_connect_and_clear_error())
@@ -91,6 +215,7 @@ pub fn watch(options: &Options, _watch: &WatchCommand) -> anyhow::Result<()> {
 pub async fn wait_changes(
     rx: &mut watch::Receiver<()>,
     retry_deadline: Option<Instant>,
+    stable_time: Duration,
 ) -> anyhow::Result<()> {
     if let Some(retry_deadline) = retry_deadline {
         let timeo = retry_deadline
@@ -115,7 +240,7 @@ pub async fn wait_changes(
         log::debug!("Change notification received. Waiting to stabilize.");
     }
     loop {
-        match timeout(STABLE_TIME, rx.changed()).await {
+        match timeout(stable_time, rx.changed()).await {
             Ok(Ok(())) => continue,
             Ok(Err(e)) => {
                 anyhow::bail!("error receiving from watch: {:#}", e);
@@ -132,22 +257,95 @@ async fn watch_loop(mut rx: watch::Receiver<()>, ctx: &mut WatchContext) -> anyh
         {
             let ctrl_c = Interrupt::ctrl_c();
             tokio::select! {
-                _ = wait_changes(&mut rx, retry_deadline) => (),
+                _ = wait_changes(&mut rx, retry_deadline, ctx.debounce) => (),
                 res = ctrl_c.wait_result() => res?,
             };
         }
-        retry_deadline = None;
-        if let Err(e) = ctx.do_update().await {
-            log::error!(
-                "Error updating database: {:#}. \
-                         Will retry in 10s.",
-                e
-            );
-            retry_deadline = Some(Instant::now() + Duration::from_secs(10));
+        let retrying = retry_deadline.take().is_some();
+        let changes = mem::take(&mut *ctx.changes.lock().unwrap());
+        if changes.is_empty() {
+            // Nothing changed since the failed update, but the database
+            // might be reachable now
+            if !retrying {
+                continue;
+            }
+        } else {
+            ctx.emit(Event::ChangeDetected { paths: &changes });
+        }
+        // Files watched only for hooks and queries don't need schema update
+        let schema_changed = changes.is_empty()
+            || changes.iter().any(|path| {
+                !ctx.hooks
+                    .files
+                    .iter()
+                    .any(|files| ctx.glob_matches(&files.glob, path))
+                    && !ctx.is_query_file(path)
+            });
+        if schema_changed {
+            if let Err(e) = ctx.do_update().await {
+                log::error!(
+                    "Error updating database: {:#}. \
+                             Will retry in 10s.",
+                    e
+                );
+                retry_deadline = Some(Instant::now() + Duration::from_secs(10));
+            }
+        } else if !ctx.last_error && changes.iter().any(|path| ctx.is_query_file(path)) {
+            // Queries are checked on every schema update anyway
+            match ctx.connector.connect().await {
+                Ok(mut cli) => ctx.check_queries(&mut cli).await,
+                Err(e) => log::error!("Cannot check queries: {:#}", e),
+            }
         }
+        ctx.run_file_hooks(&changes).await;
     }
 }
 
+/// Returns paths changed by the filesystem event, unless it has none
+/// that need an update.
+fn changed_paths(
+    base_dir: &Path,
+    ignore: &[glob::Pattern],
+    event: notify::Event,
+) -> Option<Vec<PathBuf>> {
+    // Reading files doesn't change them
+    if event.kind.is_access() {
+        return None;
+    }
+    let paths: Vec<_> = event
+        .paths
+        .into_iter()
+        .filter(|path| !is_ignored(base_dir, ignore, path))
+        .collect();
+    (!paths.is_empty()).then_some(paths)
+}
+
+/// Returns whether the path matches an ignore glob, either by file name
+/// or relative to the base directory.
+fn is_ignored(base_dir: &Path, ignore: &[glob::Pattern], path: &Path) -> bool {
+    let rel_path = path.strip_prefix(base_dir).unwrap_or(path);
+    let fname = path.file_name().map(Path::new);
+    ignore
+        .iter()
+        .any(|glob| glob.matches_path(rel_path) || fname.map_or(false, |f| glob.matches_path(f)))
+}
+
+/// Returns the directory to watch for the glob: the longest path prefix
+/// without wildcards.
+fn glob_base(project_dir: &Path, glob: &glob::Pattern) -> PathBuf {
+    let mut base = project_dir.to_path_buf();
+    let path = Path::new(glob.as_str());
+    let parts = path.parent().map(|p| p.components()).into_iter().flatten();
+    for part in parts {
+        let part = part.as_os_str().to_string_lossy();
+        if part.contains(['*', '?', '[']) {
+            break;
+        }
+        base.push(&*part);
+    }
+    base
+}
+
 impl WatchContext {
     async fn do_update(&mut self) -> anyhow::Result<()> {
         let bar = ProgressBar::new_spinner();
@@ -162,22 +360,120 @@ impl WatchContext {
 
         bar.finish_and_clear();
         match result {
-            Ok(()) => {
+            Ok(ddl) => {
                 if self.last_error {
                     clear_error(&mut cli).await;
                     self.last_error = false;
                     eprintln!("Resolved. Schema is up to date now.");
+                    self.emit(Event::Cleared);
+                }
+                self.emit(Event::Applied { ddl });
+                self.check_queries(&mut cli).await;
+                if let Some(command) = &self.hooks.on_success {
+                    self.run_hook("on-success", command).await;
                 }
             }
             Err(e) => {
                 eprintln!("Schema migration error: {e:#}");
-                set_error(&mut cli, e).await;
+                let error = ErrorJson::from(e);
+                self.emit(Event::Error { error: &error });
+                set_error(&mut cli, &error).await;
                 // TODO(tailhook) probably only print if error doesn't match
                 self.last_error = true;
+                if let Some(command) = &self.hooks.on_failure {
+                    self.run_hook("on-failure", command).await;
+                }
             }
         }
         Ok(())
     }
+    fn glob_matches(&self, glob: &glob::Pattern, path: &Path) -> bool {
+        let rel_path = path.strip_prefix(&self.project_dir).unwrap_or(path);
+        glob.matches_path(rel_path)
+    }
+    fn is_query_file(&self, path: &Path) -> bool {
+        self.queries
+            .as_ref()
+            .map_or(false, |q| q.matches(&self.project_dir, path))
+    }
+    async fn check_queries(&mut self, cli: &mut Connection) {
+        let Some(queries) = &mut self.queries else {
+            return;
+        };
+        let results = match queries.check(&self.project_dir, cli).await {
+            Ok(results) => results,
+            Err(e) => {
+                log::error!("Cannot check queries: {:#}", e);
+                return;
+            }
+        };
+        let mut failed = 0;
+        for (path, outcome) in &results {
+            match outcome {
+                Outcome::Ok => {}
+                Outcome::Failed { text, error } => {
+                    failed += 1;
+                    let fname = path.display().to_string();
+                    print_query_error(error, text, false, &fname)
+                        .map_err(|e| log::error!("Cannot print error: {:#}", e))
+                        .ok();
+                    let error = ErrorJson::query(path, text, error);
+                    self.emit(Event::QueryError {
+                        file: path,
+                        error: &error,
+                    });
+                }
+                Outcome::CardinalityChanged { old, new } => {
+                    print::warn(format!(
+                        "Result cardinality of {} changed from {} to {}.",
+                        path.display(),
+                        old,
+                        new,
+                    ));
+                    self.emit(Event::CardinalityChanged {
+                        file: path,
+                        old,
+                        new,
+                    });
+                }
+            }
+        }
+        if failed > 0 {
+            print::error(format!(
+                "{} of {} queries failed to compile.",
+                failed,
+                results.len()
+            ));
+        }
+        self.emit(Event::QueriesChecked {
+            total: results.len(),
+            failed,
+        });
+    }
+    async fn run_file_hooks(&self, changes: &[PathBuf]) {
+        for files in &self.hooks.files {
+            if changes
+                .iter()
+                .any(|path| self.glob_matches(&files.glob, path))
+            {
+                self.run_hook(files.glob.as_str(), &files.run).await;
+            }
+        }
+    }
+    /// Runs the hook ignoring the changes it makes. Changes made by the
+    /// user while it runs are ignored too.
+    async fn run_hook(&self, name: &str, command: &str) {
+        self.hook_running.store(true, Ordering::SeqCst);
+        hooks::run(&self.project_dir, name, command).await;
+        // Filesystem events are delivered with a delay
+        tokio::time::sleep(self.debounce).await;
+        self.hook_running.store(false, Ordering::SeqCst);
+    }
+    fn emit(&self, event: Event) {
+        if self.json {
+            println!("{}", serde_json::to_string(&event).unwrap());
+        }
+    }
     async fn try_connect_and_clear_error(&mut self) -> anyhow::Result<()> {
         if self.last_error {
             let mut cli = self.connector.connect().await?;
@@ -189,7 +485,21 @@ impl WatchContext {
 
 impl From<anyhow::Error> for ErrorJson {
     fn from(err: anyhow::Error) -> ErrorJson {
-        if let Some(err) = err.downcast_ref::<Error>() {
+        // Schema errors have a position in the schema file
+        let esdl = err.downcast_ref::<EsdlError>();
+        let context = esdl
+            .and_then(|e| e.location.as_ref())
+            .map(|loc| ErrorContext {
+                line: loc.line as u32,
+                col: loc.column as u32,
+                start: loc.start,
+                end: loc.end,
+                filename: loc.path.display().to_string(),
+            });
+        let edgedb_error = esdl
+            .map(|e| &e.error)
+            .or_else(|| err.downcast_ref::<Error>());
+        if let Some(err) = edgedb_error {
             ErrorJson {
                 kind: "WatchError",
                 message: format!(
@@ -204,7 +514,7 @@ impl From<anyhow::Error> for ErrorJson {
                         .into(),
                 ),
                 details: None,
-                context: None, // TODO(tailhook)
+                context,
             }
         } else {
             ErrorJson {
@@ -226,6 +536,35 @@ impl From<anyhow::Error> for ErrorJson {
     }
 }
 
+impl ErrorJson {
+    fn query(path: &Path, text: &str, err: &Error) -> ErrorJson {
+        let context = match (err.position_start(), err.position_end()) {
+            (Some(start), Some(end)) => {
+                let (line, col) = line_column(text, start);
+                Some(ErrorContext {
+                    line: line as u32,
+                    col: col as u32,
+                    start,
+                    end,
+                    filename: path.display().to_string(),
+                })
+            }
+            _ => None,
+        };
+        ErrorJson {
+            kind: "QueryError",
+            message: format!(
+                "{}: {}",
+                err.kind_name(),
+                err.initial_message().unwrap_or("")
+            ),
+            hint: err.hint().map(|h| h.into()),
+            details: err.details().map(|d| d.into()),
+            context,
+        }
+    }
+}
+
 async fn clear_error(cli: &mut Connection) {
     let res = cli
         .execute("CONFIGURE CURRENT DATABASE RESET force_database_error", &())
@@ -234,8 +573,8 @@ async fn clear_error(cli: &mut Connection) {
     log::error!("Cannot clear database error state: {:#}", e);
 }
 
-async fn set_error(cli: &mut Connection, e: anyhow::Error) {
-    let data = serde_json::to_string(&ErrorJson::from(e)).unwrap();
+async fn set_error(cli: &mut Connection, error: &ErrorJson) {
+    let data = serde_json::to_string(error).unwrap();
     let res = cli
         .execute(
             &format!(
@@ -248,3 +587,95 @@ async fn set_error(cli: &mut Connection, e: anyhow::Error) {
     let Err(e) = res else { return };
     log::error!("Cannot set database error state: {:#}", e);
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use edgedb_errors::{ErrorKind, InvalidReferenceError};
+    use notify::event::{AccessKind, AccessMode, CreateKind, DataChange, ModifyKind};
+    use notify::{Event, EventKind};
+
+    use super::{changed_paths, is_ignored, ErrorJson, DEFAULT_IGNORE};
+
+    #[test]
+    fn ignore() {
+        let ignore: Vec<_> = DEFAULT_IGNORE
+            .iter()
+            .chain(&["tmp/**"])
+            .map(|g| glob::Pattern::new(g).unwrap())
+            .collect();
+        let base = Path::new("/proj");
+        let ignored = |p: &str| is_ignored(base, &ignore, Path::new(p));
+        assert!(ignored("/proj/dbschema/.default.esdl.swp"));
+        assert!(ignored("/proj/dbschema/default.esdl~"));
+        assert!(ignored("/proj/dbschema/4913"));
+        assert!(ignored("/proj/dbschema/.#default.esdl"));
+        assert!(ignored("/proj/tmp/x/default.esdl"));
+        assert!(!ignored("/proj/dbschema/default.esdl"));
+        assert!(!ignored("/other/tmp/default.esdl"));
+    }
+
+    #[test]
+    fn event_filter() {
+        let ignore: Vec<_> = DEFAULT_IGNORE
+            .iter()
+            .map(|g| glob::Pattern::new(g).unwrap())
+            .collect();
+        let changed = |kind, paths: &[&str]| {
+            let event = paths
+                .iter()
+                .fold(Event::new(kind), |event, p| event.add_path(p.into()));
+            changed_paths(Path::new("/proj"), &ignore, event)
+        };
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        let close = EventKind::Access(AccessKind::Close(AccessMode::Write));
+        assert_eq!(
+            changed(modify, &["/proj/dbschema/default.esdl"]),
+            Some(vec!["/proj/dbschema/default.esdl".into()])
+        );
+        assert_eq!(changed(close, &["/proj/dbschema/default.esdl"]), None);
+        assert_eq!(
+            changed(
+                EventKind::Create(CreateKind::File),
+                &["/proj/dbschema/.default.esdl.swp", "/proj/dbschema/4913"]
+            ),
+            None
+        );
+        assert_eq!(
+            changed(
+                modify,
+                &[
+                    "/proj/dbschema/default.esdl~",
+                    "/proj/dbschema/default.esdl"
+                ]
+            ),
+            Some(vec!["/proj/dbschema/default.esdl".into()])
+        );
+    }
+
+    #[test]
+    fn query_error() {
+        let text = "SELECT User {\n    name,\n    nick\n}";
+        let start = text.find("nick").unwrap();
+        let headers = HashMap::from([
+            (0xFFF1, start.to_string().into()),
+            (0xFFF2, (start + 4).to_string().into()),
+        ]);
+        let err = InvalidReferenceError::with_message(
+            "object type 'default::User' has no link or property 'nick'",
+        )
+        .with_headers(headers);
+        let json = ErrorJson::query(Path::new("queries/user.edgeql"), text, &err);
+        assert_eq!(json.kind, "QueryError");
+        let context = json.context.unwrap();
+        assert_eq!((context.line, context.col), (3, 5));
+        assert_eq!((context.start, context.end), (start, start + 4));
+        assert_eq!(context.filename, "queries/user.edgeql");
+
+        let err = InvalidReferenceError::with_message("no position");
+        let json = ErrorJson::query(Path::new("queries/user.edgeql"), text, &err);
+        assert!(json.context.is_none());
+    }
+}
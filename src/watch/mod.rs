@@ -1,6 +1,8 @@
 pub mod options;
 
+mod hooks;
 mod main;
+mod queries;
 
-pub use main::wait_changes;
 pub use main::watch;
+pub use main::{wait_changes, STABLE_TIME};
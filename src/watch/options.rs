@@ -1,4 +1,9 @@
-use crate::options::ConnectionOptions;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::ValueHint;
+
+use crate::options::{parse_duration, ConnectionOptions};
 
 #[derive(clap::Args, Debug, Clone)]
 pub struct WatchCommand {
@@ -8,4 +13,33 @@ pub struct WatchCommand {
     /// Print DDLs applied to the schema.
     #[arg(short = 'v', long)]
     pub verbose: bool,
+
+    /// Print events (start, changes, applied DDL, errors with file
+    /// positions) to stdout as JSON, one object per line.
+    #[arg(long)]
+    pub json: bool,
+
+    /// Compile `.edgeql` files matching the glob (relative to the project
+    /// directory) after each schema update and whenever they change.
+    /// Reports compile errors and changes of result cardinality.
+    #[arg(long, value_name = "GLOB")]
+    pub queries: Option<String>,
+
+    /// Schema directory to watch. The default is `project.schema-dir`
+    /// from `edgedb.toml`, or `dbschema/` when run outside of a project
+    /// (the database is chosen by connection options then).
+    #[arg(long, value_hint=ValueHint::DirPath)]
+    pub schema_dir: Option<PathBuf>,
+
+    /// Wait until files are unchanged for this long (e.g. '500ms')
+    /// before applying changes.
+    #[arg(long, value_name="TIME", default_value="100ms", value_parser=parse_duration)]
+    pub debounce: Duration,
+
+    /// Ignore changes to files matching the glob (matched against
+    /// the file name and the path relative to the project directory).
+    /// Editor swap and backup files are always ignored. Can be
+    /// specified multiple times.
+    #[arg(long, value_name = "GLOB")]
+    pub ignore: Vec<String>,
 }
@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use edgedb_errors::Error;
+use edgedb_protocol::client_message::{Cardinality, CompilationOptions, IoFormat};
+use edgedb_protocol::common::Capabilities;
+use tokio::fs;
+
+use crate::connect::Connection;
+
+/// Query files validated by `watch --queries`
+pub struct Queries {
+    /// Glob relative to the project directory
+    pub glob: glob::Pattern,
+    /// Migrations are `.edgeql` files too, so the schema directory is
+    /// excluded. Must be in the same form as the project directory
+    /// and the watched paths.
+    schema_dir: PathBuf,
+    /// Result cardinality of each query at the last successful check
+    cardinality: BTreeMap<PathBuf, String>,
+}
+
+pub enum Outcome {
+    Ok,
+    Failed { text: String, error: Error },
+    CardinalityChanged { old: String, new: String },
+}
+
+impl Queries {
+    pub fn new(glob: &str, schema_dir: &Path) -> anyhow::Result<Queries> {
+        Ok(Queries {
+            glob: glob::Pattern::new(glob).with_context(|| format!("invalid glob {:?}", glob))?,
+            schema_dir: schema_dir.to_path_buf(),
+            cardinality: BTreeMap::new(),
+        })
+    }
+
+    /// Returns whether the changed path is a query file
+    pub fn matches(&self, project_dir: &Path, path: &Path) -> bool {
+        let rel_path = path.strip_prefix(project_dir).unwrap_or(path);
+        self.glob.matches_path(rel_path) && !path.starts_with(&self.schema_dir)
+    }
+
+    /// Compiles every matching query against the current schema. Files are
+    /// listed again each time, so new files are picked up.
+    pub async fn check(
+        &mut self,
+        project_dir: &Path,
+        cli: &mut Connection,
+    ) -> anyhow::Result<Vec<(PathBuf, Outcome)>> {
+        let dir = project_dir
+            .to_str()
+            .context("project path is not valid unicode")?;
+        let pattern = format!("{}/{}", glob::Pattern::escape(dir), self.glob.as_str());
+        let mut paths = glob::glob(&pattern)?.collect::<Result<Vec<_>, _>>()?;
+        paths.retain(|path| !path.starts_with(&self.schema_dir));
+        paths.sort();
+
+        let flags = CompilationOptions {
+            implicit_limit: None,
+            implicit_typenames: false,
+            implicit_typeids: false,
+            explicit_objectids: true,
+            allow_capabilities: Capabilities::ALL,
+            io_format: IoFormat::Binary,
+            expected_cardinality: Cardinality::Many,
+        };
+        let mut results = Vec::with_capacity(paths.len());
+        for path in paths {
+            let text = fs::read_to_string(&path)
+                .await
+                .with_context(|| format!("cannot read {:?}", path))?;
+            let outcome = match cli.parse(&flags, &text).await {
+                Ok(desc) => {
+                    self.update_cardinality(&path, format!("{:?}", desc.result_cardinality))
+                }
+                Err(error) if cli.is_consistent() => Outcome::Failed { text, error },
+                Err(error) => return Err(error)?,
+            };
+            results.push((path, outcome));
+        }
+        self.forget_removed(results.iter().map(|(path, _)| path));
+        Ok(results)
+    }
+
+    fn update_cardinality(&mut self, path: &Path, new: String) -> Outcome {
+        match self.cardinality.insert(path.to_path_buf(), new.clone()) {
+            Some(old) if old != new => Outcome::CardinalityChanged { old, new },
+            _ => Outcome::Ok,
+        }
+    }
+
+    fn forget_removed<'a>(&mut self, existing: impl Iterator<Item = &'a PathBuf>) {
+        let existing: Vec<_> = existing.collect();
+        self.cardinality.retain(|path, _| existing.contains(&path));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::{Outcome, Queries};
+
+    #[test]
+    fn matches() {
+        let queries = Queries::new("**/*.edgeql", Path::new("/proj/dbschema")).unwrap();
+        let project = Path::new("/proj");
+        assert!(queries.matches(project, Path::new("/proj/queries/user.edgeql")));
+        assert!(queries.matches(project, Path::new("/proj/user.edgeql")));
+        assert!(!queries.matches(project, Path::new("/proj/user.esdl")));
+        assert!(!queries.matches(
+            project,
+            Path::new("/proj/dbschema/migrations/00001-m1abcde.edgeql")
+        ));
+    }
+
+    #[test]
+    fn cardinality() {
+        let mut queries = Queries::new("*.edgeql", Path::new("/proj/dbschema")).unwrap();
+        let a = PathBuf::from("/proj/a.edgeql");
+        let b = PathBuf::from("/proj/b.edgeql");
+        let mut update = |path: &Path, card: &str| queries.update_cardinality(path, card.into());
+        assert!(matches!(update(&a, "Many"), Outcome::Ok));
+        assert!(matches!(update(&b, "One"), Outcome::Ok));
+        assert!(matches!(update(&a, "Many"), Outcome::Ok));
+        assert!(matches!(
+            update(&a, "AtMostOne"),
+            Outcome::CardinalityChanged { old, new } if old == "Many" && new == "AtMostOne"
+        ));
+        assert!(matches!(update(&a, "AtMostOne"), Outcome::Ok));
+
+        // A file created again is a new query
+        queries.forget_removed([&a].into_iter());
+        assert!(matches!(
+            queries.update_cardinality(&b, "Many".into()),
+            Outcome::Ok
+        ));
+        assert_eq!(queries.cardinality.len(), 2);
+    }
+}
@@ -115,3 +115,112 @@ fn dump_restore_all() {
     new_instance.0.stop();
     println!("query");
 }
+
+#[test]
+fn restore_json_progress() {
+    std::fs::create_dir_all("./tmp").expect("can create directory");
+    SERVER
+        .admin_cmd()
+        .arg("database")
+        .arg("create")
+        .arg("dump_03")
+        .assert()
+        .success();
+    SERVER
+        .database_cmd("dump_03")
+        .arg("query")
+        .arg("CREATE TYPE Hello { CREATE REQUIRED PROPERTY name -> str; }")
+        .arg("INSERT Hello { name := 'world' }")
+        .assert()
+        .success();
+    SERVER
+        .database_cmd("dump_03")
+        .arg("dump")
+        .arg("./tmp/dump_03.dump")
+        .assert()
+        .success();
+    SERVER
+        .admin_cmd()
+        .arg("database")
+        .arg("create")
+        .arg("restore_03")
+        .assert()
+        .success();
+    SERVER
+        .database_cmd("restore_03")
+        .arg("restore")
+        .arg("--json-progress")
+        .arg("./tmp/dump_03.dump")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(r#""event":"finished""#));
+    SERVER
+        .database_cmd("restore_03")
+        .arg("query")
+        .arg("SELECT Hello.name")
+        .assert()
+        .success()
+        .stdout("\"world\"\n");
+}
+
+#[test]
+fn dump_restore_split() {
+    std::fs::create_dir_all("./tmp").expect("can create directory");
+    SERVER
+        .admin_cmd()
+        .arg("database")
+        .arg("create")
+        .arg("dump_04")
+        .assert()
+        .success();
+    SERVER
+        .database_cmd("dump_04")
+        .arg("query")
+        .arg("CREATE TYPE Hello { CREATE REQUIRED PROPERTY name -> str; }")
+        .arg("INSERT Hello { name := 'world' }")
+        .assert()
+        .success();
+    SERVER
+        .database_cmd("dump_04")
+        .arg("dump")
+        .arg("--split-size=1")
+        .arg("./tmp/dump_04.dump")
+        .assert()
+        .success();
+    assert!(std::path::Path::new("./tmp/dump_04.dump.000").exists());
+    // chunks left from a larger earlier dump are removed
+    let chunks = (0..)
+        .take_while(|i| std::path::Path::new(&format!("./tmp/dump_04.dump.{i:03}")).exists())
+        .count();
+    for i in chunks..chunks + 2 {
+        std::fs::write(format!("./tmp/dump_04.dump.{i:03}"), "garbage").unwrap();
+    }
+    SERVER
+        .database_cmd("dump_04")
+        .arg("dump")
+        .arg("--split-size=1")
+        .arg("./tmp/dump_04.dump")
+        .assert()
+        .success();
+    assert!(!std::path::Path::new(&format!("./tmp/dump_04.dump.{chunks:03}")).exists());
+    SERVER
+        .admin_cmd()
+        .arg("database")
+        .arg("create")
+        .arg("restore_04")
+        .assert()
+        .success();
+    SERVER
+        .database_cmd("restore_04")
+        .arg("restore")
+        .arg("./tmp/dump_04.dump.000")
+        .assert()
+        .success();
+    SERVER
+        .database_cmd("restore_04")
+        .arg("query")
+        .arg("SELECT Hello.name")
+        .assert()
+        .success()
+        .stdout("\"world\"\n");
+}
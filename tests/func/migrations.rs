@@ -114,6 +114,42 @@ fn initial() {
             Last migration: \
             m12bulrbounwj3oj5xsspa7gj676azrog6ndi45iyuwrwzvawkxraa.\n",
         ));
+    SERVER
+        .admin_cmd()
+        .arg("--branch=initial")
+        .arg("migration")
+        .arg("status")
+        .arg("--json")
+        .arg("--schema-dir=tests/migrations/db1/initial")
+        .assert()
+        .success()
+        .stdout(contains(
+            r#""applied_revision": "m12bulrbounwj3oj5xsspa7gj676azrog6ndi45iyuwrwzvawkxraa""#,
+        ))
+        .stdout(contains(r#""up_to_date": true"#));
+    SERVER
+        .admin_cmd()
+        .arg("--branch=status_json_missing")
+        .arg("migration")
+        .arg("status")
+        .arg("--json")
+        .arg("--schema-dir=tests/migrations/db1/initial")
+        .assert()
+        .code(1)
+        .stdout(contains(r#""error": "#));
+    SERVER
+        .admin_cmd()
+        .arg("migration")
+        .arg("log")
+        .arg("--from-fs")
+        .arg("--json")
+        .arg("--schema-dir=tests/migrations/db1/initial")
+        .assert()
+        .success()
+        .stdout(contains(
+            r#""id": "m12bulrbounwj3oj5xsspa7gj676azrog6ndi45iyuwrwzvawkxraa""#,
+        ))
+        .stdout(contains(r#""parent": null"#));
     SERVER
         .admin_cmd()
         .arg("--branch=initial")
@@ -660,6 +696,19 @@ fn modified1() {
     )
     .unwrap();
 
+    SERVER
+        .admin_cmd()
+        .arg("--branch=modified1")
+        .arg("migration")
+        .arg("create")
+        .arg("--squash")
+        .arg("--from=m13wjyi")
+        .arg("--to=m13wjyi")
+        .arg("--non-interactive")
+        .arg("--schema-dir=tests/migrations/db1/squash")
+        .assert()
+        .failure()
+        .stderr(contains("Nothing to squash"));
     SERVER
         .admin_cmd()
         .arg("--branch=modified1")
@@ -711,6 +760,225 @@ fn modified1() {
         );
 }
 
+/// Returns `(file name, id, parent id)` of the migrations in `dir`
+fn migration_files(dir: &str) -> Vec<(String, String, String)> {
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.ends_with(".edgeql"))
+        .collect();
+    files.sort();
+    files
+        .into_iter()
+        .map(|name| {
+            let text = fs::read_to_string(Path::new(dir).join(&name)).unwrap();
+            let mut words = text.split_whitespace();
+            assert_eq!(words.next(), Some("CREATE"));
+            assert_eq!(words.next(), Some("MIGRATION"));
+            let id = words.next().unwrap().to_string();
+            assert_eq!(words.next(), Some("ONTO"));
+            let parent = words.next().unwrap().to_string();
+            (name, id, parent)
+        })
+        .collect()
+}
+
+#[test]
+fn squash_range() {
+    const DIR: &str = "tests/migrations/squash_range";
+    SERVER
+        .admin_cmd()
+        .arg("database")
+        .arg("create")
+        .arg("squash_range")
+        .assert()
+        .success();
+    fs::remove_dir_all(DIR).ok();
+    fs::create_dir_all(format!("{DIR}/migrations")).unwrap();
+    for schema in [
+        "module default { type A; }",
+        "module default { type A; type B; }",
+        "module default { type A { property a -> str; }; type B; }",
+        "module default { type A { property a -> str; }; type B; type C; }",
+    ] {
+        fs::write(format!("{DIR}/default.esdl"), schema).unwrap();
+        SERVER
+            .admin_cmd()
+            .arg("--branch=squash_range")
+            .arg("migration")
+            .arg("create")
+            .arg("--non-interactive")
+            .arg(format!("--schema-dir={DIR}"))
+            .assert()
+            .success();
+        SERVER
+            .admin_cmd()
+            .arg("--branch=squash_range")
+            .arg("migrate")
+            .arg(format!("--schema-dir={DIR}"))
+            .assert()
+            .success();
+    }
+    let old = migration_files(&format!("{DIR}/migrations"));
+    assert_eq!(old.len(), 4);
+
+    SERVER
+        .admin_cmd()
+        .arg("--branch=squash_range")
+        .arg("migration")
+        .arg("create")
+        .arg("--squash")
+        .arg(format!("--from={}", &old[1].1[..10]))
+        .arg(format!("--to={}", &old[2].1[..10]))
+        .arg("--non-interactive")
+        .arg(format!("--schema-dir={DIR}"))
+        .assert()
+        .success()
+        .stderr(contains("Squash is complete"));
+
+    let new = migration_files(&format!("{DIR}/migrations"));
+    assert_eq!(new.len(), 3);
+    // The first revision is untouched, the squashed one follows it, and
+    // the revision after the range is renumbered and points to it
+    assert_eq!(new[0], old[0]);
+    let (squashed, last) = (&new[1], &new[2]);
+    assert_eq!(squashed.0, format!("00002-{}.edgeql", &squashed.1[..7]));
+    assert_eq!(squashed.2, old[0].1);
+    assert_eq!(last.0, format!("00003-{}.edgeql", &last.1[..7]));
+    assert_eq!(last.2, squashed.1);
+    assert_ne!(last.1, old[3].1);
+    let squashed_text = fs::read_to_string(format!("{DIR}/migrations/{}", squashed.0)).unwrap();
+    assert!(squashed_text.contains("CREATE TYPE default::B"));
+    assert!(squashed_text.contains("CREATE PROPERTY a"));
+
+    // Databases at the first squashed revision get the rest of the range,
+    // others only need their revision id to be rewritten
+    let fixups = migration_files(&format!("{DIR}/fixups"));
+    let fixup_from = |parent: &str, target: &str| {
+        let fixup = fixups
+            .iter()
+            .find(|f| f.0 == format!("{parent}-{target}.edgeql"))
+            .unwrap_or_else(|| panic!("no fixup from {parent} in {fixups:?}"));
+        assert_eq!(fixup.2, parent);
+        fs::read_to_string(format!("{DIR}/fixups/{}", fixup.0)).unwrap()
+    };
+    assert_eq!(fixups.len(), 3);
+    assert!(fixup_from(&old[1].1, &squashed.1).contains("CREATE PROPERTY a"));
+    assert!(!fixup_from(&old[2].1, &squashed.1).contains("CREATE PROPERTY"));
+    assert!(!fixup_from(&old[3].1, &last.1).contains("CREATE TYPE"));
+
+    SERVER
+        .admin_cmd()
+        .arg("--branch=squash_range")
+        .arg("migrate")
+        .arg(format!("--schema-dir={DIR}"))
+        .assert()
+        .success();
+    SERVER
+        .admin_cmd()
+        .arg("--branch=squash_range")
+        .arg("migration")
+        .arg("log")
+        .arg("--from-db")
+        .arg("--newest-first")
+        .arg("--limit=1")
+        .assert()
+        .success()
+        .stdout(format!("{}\n", last.1));
+}
+
+#[test]
+fn squash_range_retargets_fixups() {
+    const DIR: &str = "tests/migrations/squash_range_fixups";
+    SERVER
+        .admin_cmd()
+        .arg("database")
+        .arg("create")
+        .arg("squash_range_fixups")
+        .assert()
+        .success();
+    fs::remove_dir_all(DIR).ok();
+    fs::create_dir_all(format!("{DIR}/migrations")).unwrap();
+    for schema in [
+        "module default { type A; }",
+        "module default { type A; type B; }",
+        "module default { type A; type B; type C; }",
+        "module default { type A; type B; type C; type D; }",
+        "module default { type A; type B; type C; type D; type E; }",
+    ] {
+        fs::write(format!("{DIR}/default.esdl"), schema).unwrap();
+        SERVER
+            .admin_cmd()
+            .arg("--branch=squash_range_fixups")
+            .arg("migration")
+            .arg("create")
+            .arg("--non-interactive")
+            .arg(format!("--schema-dir={DIR}"))
+            .assert()
+            .success();
+        SERVER
+            .admin_cmd()
+            .arg("--branch=squash_range_fixups")
+            .arg("migrate")
+            .arg(format!("--schema-dir={DIR}"))
+            .assert()
+            .success();
+    }
+    let squash = |from: &str, to: &str| {
+        SERVER
+            .admin_cmd()
+            .arg("--branch=squash_range_fixups")
+            .arg("migration")
+            .arg("create")
+            .arg("--squash")
+            .arg(format!("--from={}", &from[..10]))
+            .arg(format!("--to={}", &to[..10]))
+            .arg("--non-interactive")
+            .arg(format!("--schema-dir={DIR}"))
+            .assert()
+            .success();
+    };
+    let old = migration_files(&format!("{DIR}/migrations"));
+    squash(&old[1].1, &old[2].1);
+    let mid = migration_files(&format!("{DIR}/migrations"));
+    assert_eq!(mid.len(), 4);
+    // The database is still at the last original revision, so it can only
+    // reach the final one through the fixup written by the first squash
+    squash(&mid[1].1, &mid[2].1);
+    let new = migration_files(&format!("{DIR}/migrations"));
+    assert_eq!(new.len(), 3);
+    let last = &new[2].1;
+    assert_ne!(last, &mid[3].1);
+
+    let fixups = migration_files(&format!("{DIR}/fixups"));
+    let target = |f: &(String, String, String)| f.0.trim_end_matches(".edgeql").to_owned();
+    assert!(fixups
+        .iter()
+        .any(|f| target(f) == format!("{}-{last}", old[4].1)));
+    assert!(!fixups
+        .iter()
+        .any(|f| target(f).ends_with(&format!("-{}", mid[3].1))));
+
+    SERVER
+        .admin_cmd()
+        .arg("--branch=squash_range_fixups")
+        .arg("migrate")
+        .arg(format!("--schema-dir={DIR}"))
+        .assert()
+        .success();
+    SERVER
+        .admin_cmd()
+        .arg("--branch=squash_range_fixups")
+        .arg("migration")
+        .arg("log")
+        .arg("--from-db")
+        .arg("--newest-first")
+        .arg("--limit=1")
+        .assert()
+        .success()
+        .stdout(format!("{last}\n"));
+}
+
 #[test]
 fn error() {
     SERVER
@@ -781,8 +1049,10 @@ fn modified2_interactive() {
         cmd.arg("migration").arg("create");
         cmd.arg("--schema-dir=tests/migrations/db1/modified2");
     });
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,e,c,b,s,q,?]").unwrap();
     cmd.send_line("y").unwrap();
+    cmd.exp_string("Describe the migration").unwrap();
+    cmd.send_line("").unwrap();
     cmd.exp_string(
         "Created \
         tests/migrations/db1/modified2/migrations/00002-m13wjyi.edgeql, \
@@ -826,6 +1096,73 @@ fn modified2_interactive() {
         .stderr(ends_with("No schema changes detected.\n"));
 }
 
+#[test]
+fn edit_interactive() {
+    fs::remove_dir_all("tests/migrations/db1/edited").ok();
+    fs::create_dir_all("tests/migrations/db1/edited/migrations").unwrap();
+    fs::copy(
+        "tests/migrations/db1/modified2/default.esdl",
+        "tests/migrations/db1/edited/default.esdl",
+    )
+    .unwrap();
+    fs::copy(
+        "tests/migrations/db1/modified2/migrations/00001-m12bulr.edgeql",
+        "tests/migrations/db1/edited/migrations/00001-m12bulr.edgeql",
+    )
+    .unwrap();
+    SERVER
+        .admin_cmd()
+        .arg("database")
+        .arg("create")
+        .arg("edited")
+        .assert()
+        .success();
+    SERVER
+        .admin_cmd()
+        .arg("--branch=edited")
+        .arg("migrate")
+        .arg("--schema-dir=tests/migrations/db1/edited")
+        .assert()
+        .success();
+
+    // The "editor" appends a data statement to the proposed DDL
+    let mut cmd = SERVER.custom_interactive(|cmd| {
+        cmd.arg("--branch=edited");
+        cmd.arg("migration").arg("create");
+        cmd.arg("--schema-dir=tests/migrations/db1/edited");
+        cmd.env(
+            "EDGEDB_EDITOR",
+            r"sed -i $s/$/\nINSERT\x20default::Type2{field2:='edited'};/",
+        );
+    });
+    cmd.exp_string("[y,n,l,e,c,b,s,q,?]").unwrap();
+    cmd.send_line("e").unwrap();
+    cmd.exp_string("Describe the migration").unwrap();
+    cmd.send_line("").unwrap();
+    cmd.exp_string("Created").unwrap();
+    let files = migration_files("tests/migrations/db1/edited/migrations");
+    let path = Path::new("tests/migrations/db1/edited/migrations").join(&files[1].0);
+    let text = fs::read_to_string(path).unwrap();
+    assert!(text.contains("CREATE TYPE default::Type2"));
+    assert!(text.contains("INSERT"));
+
+    SERVER
+        .admin_cmd()
+        .arg("--branch=edited")
+        .arg("migrate")
+        .arg("--schema-dir=tests/migrations/db1/edited")
+        .assert()
+        .success();
+    SERVER
+        .admin_cmd()
+        .arg("--branch=edited")
+        .arg("query")
+        .arg("SELECT default::Type2.field2")
+        .assert()
+        .success()
+        .stdout("\"edited\"\n");
+}
+
 #[test]
 fn modified3_interactive() {
     crate::rm_migration_files("tests/migrations/db1/modified3", &[2]);
@@ -854,16 +1191,18 @@ fn modified3_interactive() {
         cmd.arg("migration").arg("create");
         cmd.arg("--schema-dir=tests/migrations/db1/modified3");
     });
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,e,c,b,s,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,e,c,b,s,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,e,c,b,s,q,?]").unwrap();
     cmd.send_line("back").unwrap();
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,e,c,b,s,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,e,c,b,s,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
+    cmd.exp_string("Describe the migration").unwrap();
+    cmd.send_line("").unwrap();
     cmd.exp_string("Created").unwrap();
 
     SERVER
@@ -931,12 +1270,14 @@ fn prompt_id() {
         cmd.arg("migration").arg("create");
         cmd.arg("--schema-dir=tests/migrations/db2/modified1");
     });
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,e,c,b,s,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,e,c,b,s,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
     // on pre-prompt_id version this would require an extra prompt
     cmd.exp_string("extra DDL statements").unwrap();
+    cmd.exp_string("Describe the migration").unwrap();
+    cmd.send_line("").unwrap();
     cmd.exp_string("Created").unwrap();
 }
 
@@ -969,10 +1310,12 @@ fn input_required() {
         cmd.arg("migration").arg("create");
         cmd.arg("--schema-dir=tests/migrations/db3");
     });
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,e,c,b,s,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
     cmd.exp_string("cast_expr>").unwrap();
     cmd.send_line("").unwrap(); // default value
+    cmd.exp_string("Describe the migration").unwrap();
+    cmd.send_line("").unwrap();
     cmd.exp_string("Created").unwrap();
 
     crate::rm_migration_files("tests/migrations/db3", &[2]);
@@ -981,11 +1324,13 @@ fn input_required() {
         cmd.arg("migration").arg("create");
         cmd.arg("--schema-dir=tests/migrations/db3");
     });
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,e,c,b,s,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
     cmd.exp_string("cast_expr>").unwrap();
     // just add a comment to the default value
     cmd.send_line("# comment").unwrap();
+    cmd.exp_string("Describe the migration").unwrap();
+    cmd.send_line("").unwrap();
     cmd.exp_string("Created").unwrap();
 }
 
@@ -1113,3 +1458,233 @@ fn unsafe_migrations() {
         .assert()
         .success();
 }
+
+#[test]
+fn revert() {
+    SERVER
+        .admin_cmd()
+        .arg("database")
+        .arg("create")
+        .arg("db_revert")
+        .assert()
+        .success();
+    SERVER
+        .admin_cmd()
+        .arg("--branch=db_revert")
+        .arg("migrate")
+        .arg("--schema-dir=tests/migrations/db_revert")
+        .assert()
+        .success();
+    SERVER
+        .admin_cmd()
+        .arg("--branch=db_revert")
+        .arg("migration")
+        .arg("revert")
+        .arg("--to=initial")
+        .arg("--non-interactive")
+        .arg("--schema-dir=tests/migrations/db_revert")
+        .env("NO_COLOR", "1")
+        .assert()
+        .success()
+        .stdout(contains("DROP TYPE"))
+        .stderr(ends_with("Reverted to revision initial\n"));
+    SERVER
+        .admin_cmd()
+        .arg("--branch=db_revert")
+        .arg("query")
+        .arg("SELECT count(schema::ObjectType FILTER .name = 'default::Type1')")
+        .assert()
+        .success()
+        .stdout("0\n");
+    SERVER
+        .admin_cmd()
+        .arg("--branch=db_revert")
+        .arg("migration")
+        .arg("status")
+        .arg("--schema-dir=tests/migrations/db_revert")
+        .assert()
+        .code(3)
+        .stderr(contains("Database is empty, while 1 migrations"));
+    SERVER
+        .admin_cmd()
+        .arg("--branch=db_revert")
+        .arg("migration")
+        .arg("revert")
+        .arg("--to=initial")
+        .arg("--non-interactive")
+        .arg("--schema-dir=tests/migrations/db_revert")
+        .assert()
+        .success()
+        .stderr(contains("Database is already at revision initial."));
+}
+
+#[test]
+fn lint() {
+    SERVER
+        .admin_cmd()
+        .arg("migration")
+        .arg("lint")
+        .arg("--schema-dir=tests/migrations/db_revert")
+        .assert()
+        .success()
+        .stderr(contains("No issues found in 1 migration files."));
+    SERVER
+        .admin_cmd()
+        .arg("migration")
+        .arg("lint")
+        .arg("--json")
+        .arg("--schema-dir=tests/migrations/db_revert")
+        .assert()
+        .success()
+        .stdout("[]\n");
+}
+
+#[test]
+fn dry_run() {
+    SERVER
+        .admin_cmd()
+        .arg("database")
+        .arg("create")
+        .arg("db_dry_run")
+        .assert()
+        .success();
+    SERVER
+        .admin_cmd()
+        .arg("--branch=db_dry_run")
+        .arg("migrate")
+        .arg("--dry-run")
+        .arg("--rollback")
+        .arg("--schema-dir=tests/migrations/db_revert")
+        .env("NO_COLOR", "1")
+        .assert()
+        .success()
+        .stdout(contains(
+            "Would apply \
+            m12bulrbounwj3oj5xsspa7gj676azrog6ndi45iyuwrwzvawkxraa \
+            (00001-m12bulr.edgeql)\n",
+        ))
+        .stdout(contains("CREATE TYPE Type1"))
+        .stderr(contains(
+            "1 migrations applied successfully and rolled back",
+        ));
+    SERVER
+        .admin_cmd()
+        .arg("--branch=db_dry_run")
+        .arg("migration")
+        .arg("status")
+        .arg("--schema-dir=tests/migrations/db_revert")
+        .assert()
+        .code(3)
+        .stderr(contains("Database is empty, while 1 migrations"));
+}
+
+#[test]
+fn migration_test() {
+    SERVER
+        .admin_cmd()
+        .arg("database")
+        .arg("create")
+        .arg("migration_test")
+        .assert()
+        .success();
+    SERVER
+        .admin_cmd()
+        .arg("--branch=migration_test")
+        .arg("migration")
+        .arg("test")
+        .arg("--schema-dir=tests/migrations/migration_test")
+        .env("NO_COLOR", "1")
+        .assert()
+        .success()
+        .stderr(contains("PASS fixture_loaded.edgeql"))
+        .stderr(contains("1 tests passed."));
+    SERVER
+        .admin_cmd()
+        .arg("--branch=migration_test")
+        .arg("migration")
+        .arg("log")
+        .arg("--from-db")
+        .assert()
+        .success()
+        .stdout("");
+    SERVER
+        .admin_cmd()
+        .arg("--branch=migration_test")
+        .arg("migration")
+        .arg("log")
+        .arg("--graph")
+        .arg("--schema-dir=tests/migrations/migration_test")
+        .assert()
+        .success()
+        .stdout(
+            "* m12bulrbounwj3oj5xsspa7gj676azrog6ndi45iyuwrwzvawkxraa  \
+             (00001-m12bulr.edgeql, not applied)\n",
+        );
+}
+
+#[test]
+fn schema_pull() {
+    SERVER
+        .admin_cmd()
+        .arg("database")
+        .arg("create")
+        .arg("schema_pull")
+        .assert()
+        .success();
+    SERVER
+        .admin_cmd()
+        .arg("--branch=schema_pull")
+        .arg("migrate")
+        .arg("--schema-dir=tests/migrations/db_revert")
+        .assert()
+        .success();
+
+    fs::remove_dir_all("tests/migrations/schema_pull").ok();
+    SERVER
+        .admin_cmd()
+        .arg("--branch=schema_pull")
+        .arg("schema")
+        .arg("pull")
+        .arg("--non-interactive")
+        .arg("--schema-dir=tests/migrations/schema_pull")
+        .assert()
+        .success();
+    let text = fs::read_to_string("tests/migrations/schema_pull/default.esdl").unwrap();
+    assert!(text.contains("type Type1"));
+    SERVER
+        .admin_cmd()
+        .arg("--branch=schema_pull")
+        .arg("schema")
+        .arg("pull")
+        .arg("--non-interactive")
+        .arg("--schema-dir=tests/migrations/schema_pull")
+        .assert()
+        .failure()
+        .stderr(contains("use `--force`"));
+}
+
+#[test]
+fn schema_check() {
+    SERVER
+        .admin_cmd()
+        .arg("schema")
+        .arg("check")
+        .arg("--schema-dir=tests/migrations/schema_check")
+        .env("NO_COLOR", "1")
+        .assert()
+        .code(1)
+        .stderr(contains("Bracket is closed by a mismatched"))
+        .stderr(contains("tests/migrations/schema_check/a.esdl:2:12"))
+        .stderr(contains("tests/migrations/schema_check/b.esdl:4:3"))
+        .stderr(contains("Unexpected closing bracket"))
+        .stderr(contains("tests/migrations/schema_check/c.gel:6:1"))
+        .stderr(contains("Found 3 error(s)"));
+    SERVER
+        .admin_cmd()
+        .arg("schema")
+        .arg("check")
+        .arg("--schema-dir=tests/migrations/db_revert")
+        .assert()
+        .success()
+        .stderr(contains("Checked 1 schema files, no errors found."));
+}
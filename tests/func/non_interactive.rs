@@ -1,4 +1,5 @@
 use assert_cmd::Command;
+use predicates::boolean::PredicateBooleanExt;
 
 use crate::util::OutputExt;
 use crate::SERVER;
@@ -241,6 +242,127 @@ fn branch_commands() {
     // TODO: test how this works in projects
 }
 
+#[test]
+fn branch_diff() {
+    for (branch, ddl) in [
+        (
+            "diff_base",
+            "CREATE TYPE default::Item {
+                CREATE PROPERTY name -> str;
+                CREATE PROPERTY price -> int64 {
+                    CREATE CONSTRAINT max_value(100);
+                };
+                CREATE INDEX ON (.name);
+            }",
+        ),
+        (
+            "diff_other",
+            "CREATE TYPE default::Item {
+                CREATE REQUIRED PROPERTY name -> str;
+                CREATE PROPERTY weight -> int64;
+                CREATE INDEX ON (.weight);
+            }",
+        ),
+    ] {
+        SERVER
+            .admin_cmd()
+            .arg("branch")
+            .arg("create")
+            .arg("--empty")
+            .arg(branch)
+            .assert()
+            .context("create", "new empty branch")
+            .success();
+        SERVER
+            .admin_cmd()
+            .arg("--branch")
+            .arg(branch)
+            .arg("query")
+            .arg(ddl)
+            .assert()
+            .context("query", "create schema")
+            .success();
+    }
+
+    SERVER
+        .admin_cmd()
+        .arg("branch")
+        .arg("diff")
+        .arg("diff_base")
+        .arg("diff_other")
+        .env("NO_COLOR", "1")
+        .assert()
+        .context("diff", "changed property, index and constraint")
+        .success()
+        .stdout(predicates::str::contains(
+            "  ~ property default::Item.name\n      \
+            was: optional single std::str\n      \
+            now: required single std::str\n",
+        ))
+        .stdout(predicates::str::contains(
+            "  - property default::Item.price: optional single std::int64\n",
+        ))
+        .stdout(predicates::str::contains(
+            "  + property default::Item.weight: optional single std::int64\n",
+        ))
+        .stdout(predicates::str::contains(
+            "  - constraint default::Item.price std::max_value: std::max_value(100)\n",
+        ))
+        .stdout(predicates::str::contains("  - index default::Item on "))
+        .stdout(predicates::str::contains("  + index default::Item on "))
+        .stdout(predicates::str::contains("std::Object").not())
+        .stdout(predicates::str::contains("__").not());
+    SERVER
+        .admin_cmd()
+        .arg("branch")
+        .arg("diff")
+        .arg("diff_base")
+        .arg("diff_base")
+        .assert()
+        .context("diff", "branch with itself")
+        .failure();
+}
+
+#[test]
+fn branch_list() {
+    SERVER
+        .admin_cmd()
+        .arg("branch")
+        .arg("create")
+        .arg("--empty")
+        .arg("list_branch")
+        .assert()
+        .context("create", "new empty branch")
+        .success();
+    SERVER
+        .admin_cmd()
+        .arg("branch")
+        .arg("list")
+        .assert()
+        .context("list", "table output")
+        .success()
+        .stdout(predicates::str::contains("Objects (approx.)"))
+        .stdout(predicates::str::contains("list_branch"));
+    let output = SERVER
+        .admin_cmd()
+        .arg("branch")
+        .arg("list")
+        .arg("--json")
+        .arg("--sort=objects")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let branches: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    let branch = branches
+        .iter()
+        .find(|b| b["name"] == "list_branch")
+        .expect("branch is listed");
+    assert_eq!(branch["migrations"], 0);
+    assert_eq!(branch["approx_objects"], 0);
+    // The default branch may have migrations of other tests
+    assert!(["up-to-date", "behind"].contains(&branch["status"].as_str().unwrap()));
+}
+
 #[test]
 fn hash_password() {
     crate::edgedb_cli_cmd()